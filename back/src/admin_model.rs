@@ -17,4 +17,11 @@ pub struct AdminResponse {
     pub id: i32,
     pub username: String,
     // Note: Never include password in responses
-} 
\ No newline at end of file
+} 
+
+/// Query of the rule list imports, imported rules are pending unless `approve` is set
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub approve: bool,
+}
@@ -1,8 +1,8 @@
 use std::time::{SystemTime, UNIX_EPOCH};
-use axum::{extract::State, Json};
+use axum::{extract::{Request, State}, http::header, middleware::Next, response::Response, Json};
 use bcrypt::verify;
 use hyper::StatusCode;
-use jsonwebtoken::{encode, EncodingKey, Header, errors::Error};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation, errors::Error};
 use serde::{Deserialize, Serialize};
 use crate::AppState;
 
@@ -16,8 +16,6 @@ pub struct Claims {
     pub iat: usize
 }
 
-const SECRET: &[u8] = b"secret";
-
 impl Claims {
     pub fn new(username: String) -> Self {
         let now = SystemTime::now()
@@ -33,19 +31,32 @@ impl Claims {
     }
 }
 
-pub fn create_jwt(username: String) -> Result<String, Error> {
+pub fn create_jwt(username: String, secret: &[u8]) -> Result<String, Error> {
     let claims = Claims::new(username);
-    encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET))
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret))
+}
+
+pub fn validate_jwt(token: &str, secret: &[u8]) -> Result<Claims, Error> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
 }
 
-// pub fn validate_jwt(token: &str) -> Result<Claims, Error> {
-//     decode::<Claims>(
-//         token,
-//         &DecodingKey::from_secret(SECRET),
-//         &Validation::default(),
-//     )
-//     .map(|data| data.claims)
-// }
+/// Middleware of the admin routes, rejecting requests without a valid `Authorization: Bearer <token>` from the login
+pub async fn require_admin(State(state): State<AppState>, request: Request, next: Next) -> Result<Response, StatusCode> {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    validate_jwt(token, state.jwt_secret.as_bytes()).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    Ok(next.run(request).await)
+}
 
 pub async fn login_admin(
     State(state): State<AppState>,
@@ -59,7 +70,7 @@ pub async fn login_admin(
             
             if verify(&payload.password, &admin.password).unwrap_or(false) {
 
-                match create_jwt(admin.username) {
+                match create_jwt(admin.username, state.jwt_secret.as_bytes()) {
                     Ok(token) => Ok(Json(ApiResponse {
                         success: true,
                         data: Some(token),
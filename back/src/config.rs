@@ -5,6 +5,8 @@ use std::str::FromStr;
 pub struct Config {
     pub addr: SocketAddr,
     pub database_path: String,
+    /// Secret signing the admin tokens
+    pub jwt_secret: String,
 }
 
 impl Default for Config {
@@ -18,11 +20,18 @@ impl Default for Config {
 
         // Parse host into IP address
         let ip = IpAddr::from_str(&host).unwrap_or(IpAddr::from([127, 0, 0, 1]));
+
+        // Without a configured secret, tokens are signed with a random one and don't survive a restart
+        let jwt_secret = env::var("JWT_SECRET").ok().filter(|secret| !secret.is_empty()).unwrap_or_else(|| {
+            tracing::warn!("JWT_SECRET is not set, admin tokens will be invalid after a restart");
+            format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+        });
         
         Self {
             addr: SocketAddr::from((ip, port)),
             database_path: env::var("DATABASE_PATH")
                 .unwrap_or_else(|_| "../cleaner/rules.db".to_string()),
+            jwt_secret,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,62 @@
+use axum::{
+    Json,
+    extract::State,
+};
+use cleaner::{CleanOptions, CleanReport, TextReport, UrlProfile};
+use crate::models::{CleanRequest, CleanTextRequest, CleanOptionsRequest, ApiResponse};
+use crate::AppState;
+
+/// Build the engine options of a clean request
+fn clean_options(request: CleanOptionsRequest) -> CleanOptions {
+    let mut options = match request.profile.unwrap_or_default() {
+        UrlProfile::Canonical => CleanOptions::canonical(),
+        profile => CleanOptions { profile, ..Default::default() },
+    };
+
+    if let Some(subdomains) = request.strip_subdomains {
+        options.strip_subdomains = subdomains;
+    }
+    options.strip_text_fragments = request.strip_text_fragments.unwrap_or(false);
+    options.rewrites = request.rewrites.unwrap_or_default();
+    options.base = request.base;
+
+    options
+}
+
+/// Clean a URL with the approved rules, reporting what was removed and why
+pub async fn clean(
+    State(state): State<AppState>,
+    Json(payload): Json<CleanRequest>,
+) -> Json<ApiResponse<CleanReport>> {
+    let engine = state.engine.read().await;
+
+    match engine.report_with(&payload.url, &clean_options(payload.options)) {
+        Ok(report) => Json(ApiResponse {
+            success: true,
+            data: Some(report),
+            message: None,
+            error: None,
+        }),
+        Err(e) => Json(ApiResponse {
+            success: false,
+            data: None,
+            message: None,
+            error: Some(e.to_string()),
+        }),
+    }
+}
+
+/// Clean every URL found in a piece of text with the approved rules
+pub async fn clean_text(
+    State(state): State<AppState>,
+    Json(payload): Json<CleanTextRequest>,
+) -> Json<ApiResponse<TextReport>> {
+    let engine = state.engine.read().await;
+
+    Json(ApiResponse {
+        success: true,
+        data: Some(engine.report_text_with(&payload.text, &clean_options(payload.options))),
+        message: None,
+        error: None,
+    })
+}
@@ -0,0 +1,37 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use cleaner::export::{export, ExportFormat};
+use crate::models::ApiResponse;
+use crate::AppState;
+
+/// Export the approved rules as `yaml`, `json`, `clearurls` or `adguard`, without ids or statuses
+pub async fn export_rules(
+    State(state): State<AppState>,
+    Path(format): Path<String>,
+) -> Response {
+    let error = |error: String| {
+        Json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: None,
+            error: Some(error),
+        })
+        .into_response()
+    };
+
+    let format: ExportFormat = match format.parse() {
+        Ok(format) => format,
+        Err(e) => return error(e),
+    };
+
+    let config = state.db.lock().await.get_approved_config();
+
+    match config.map_err(|e| e.to_string()).and_then(|config| export(&config, format).map_err(|e| e.to_string())) {
+        Ok(document) => ([(header::CONTENT_TYPE, format.content_type())], document).into_response(),
+        Err(e) => error(format!("Failed to export rules: {e}")),
+    }
+}
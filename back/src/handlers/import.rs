@@ -0,0 +1,61 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use cleaner::import::{adguard, clearurls, ImportReport};
+use crate::admin_model::ImportQuery;
+use crate::handlers::rules::reload_engine;
+use crate::models::ApiResponse;
+use crate::AppState;
+
+/// Store the rules of an imported list, reporting the entries that couldn't be mapped to our rules
+async fn store_import(state: &AppState, import: ImportReport, approve: bool) -> Json<ApiResponse<ImportReport>> {
+    let status = if approve { "approved" } else { "pending" };
+    let result = state.db.lock().await.import_rules(&import, status);
+
+    match result {
+        Ok(stored) => {
+            if approve {
+                reload_engine(state).await;
+            }
+            Json(ApiResponse {
+                success: true,
+                message: Some(format!("Imported {stored} {status} rules, {} entries could not be mapped", import.unmapped.len())),
+                data: Some(import),
+                error: None,
+            })
+        }
+        Err(e) => Json(ApiResponse {
+            success: false,
+            data: None,
+            message: None,
+            error: Some(format!("Failed to import rules: {e}")),
+        }),
+    }
+}
+
+/// Import a ClearURLs `data.min.json` sent as the request body into the rules database
+pub async fn import_clearurls(
+    State(state): State<AppState>,
+    Query(query): Query<ImportQuery>,
+    body: String,
+) -> Json<ApiResponse<ImportReport>> {
+    match clearurls::parse(&body) {
+        Ok(import) => store_import(&state, import, query.approve).await,
+        Err(e) => Json(ApiResponse {
+            success: false,
+            data: None,
+            message: None,
+            error: Some(format!("Invalid ClearURLs data: {e}")),
+        }),
+    }
+}
+
+/// Import the `$removeparam` rules of an AdGuard or uBlock Origin filter list sent as the request body
+pub async fn import_adguard(
+    State(state): State<AppState>,
+    Query(query): Query<ImportQuery>,
+    body: String,
+) -> Json<ApiResponse<ImportReport>> {
+    store_import(&state, adguard::parse(&body), query.approve).await
+}
@@ -1,8 +1,14 @@
 pub mod health;
 pub mod rules;
 pub mod admin;
+pub mod clean;
+pub mod import;
+pub mod export;
 
 // Re-export all handlers for easy importing
 pub use health::*;
 pub use rules::*;
 pub use admin::*;
+pub use clean::*;
+pub use import::*;
+pub use export::*;
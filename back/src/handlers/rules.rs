@@ -2,10 +2,21 @@ use axum::{
     Json,
     extract::{Path, State},
 };
+use cleaner::CleanerEngine;
 use crate::models::{SubmissionRequest, ApiResponse};
 use crate::validation::is_valid_domain;
 use crate::AppState;
 
+/// Rebuild the shared cleaner engine from the approved rules
+pub(crate) async fn reload_engine(state: &AppState) {
+    let engine = state.db.lock().await.get_approved_rules().and_then(CleanerEngine::from_rules);
+
+    match engine {
+        Ok(engine) => *state.engine.write().await = engine,
+        Err(e) => tracing::error!("Failed to reload cleaner engine: {}", e),
+    }
+}
+
 /// Get all domain rules
 pub async fn get_rules(State(state): State<AppState>) -> Json<ApiResponse<Vec<cleaner::database::DomainRule>>> {
     let db = state.db.lock().await;
@@ -50,28 +61,46 @@ pub async fn submit_rule(
         });
     }
 
-    let db = state.db.lock().await;
-
-    // Always include utm_ and add any additional prefixes
-    let mut starts_with = vec!["utm_".to_string()];
-    if let Some(additional) = payload.starts_with {
-        for prefix in additional {
-            if prefix != "utm_" {
-                starts_with.push(prefix);
-            }
-        }
-    }
-
-    // Create new rule using the cleaner crate's DomainRule
+    // The global layer (utm_ etc.) always applies on top of the submitted rule
     let new_rule = cleaner::database::DomainRule {
         id: None,
         domain: payload.domain.to_lowercase(),
         keys: payload.keys,
-        starts_with,
+        starts_with: payload.starts_with.unwrap_or_default(),
+        ends_with: payload.ends_with.unwrap_or_default(),
+        patterns: payload.patterns.unwrap_or_default(),
+        regexes: payload.regexes.unwrap_or_default(),
+        path_segments: payload.path_segments.unwrap_or_default(),
+        path_suffixes: payload.path_suffixes.unwrap_or_default(),
+        path_params: payload.path_params.unwrap_or_default(),
+        ignore_case: payload.ignore_case.unwrap_or(false),
+        exceptions: payload.exceptions.unwrap_or_default(),
+        groups: payload.groups.unwrap_or_default(),
+        mode: payload.mode.unwrap_or_default(),
+        keep: payload.keep.unwrap_or_default(),
+        values: payload.values.unwrap_or_default(),
         contributors: vec![payload.contributor],
         status: "pending".to_string(),
+        ..Default::default()
     };
 
+    let config = new_rule.to_config();
+    let mut matchers = config
+        .matchers()
+        .into_iter()
+        .chain(config.path_segment_matchers())
+        .chain(config.path_suffix_matchers());
+    if let Some(invalid) = matchers.find(|m| m.validate().is_err()) {
+        return Json(ApiResponse {
+            success: false,
+            data: None,
+            message: None,
+            error: Some(format!("Invalid parameter pattern: {}", invalid.pattern)),
+        });
+    }
+
+    let db = state.db.lock().await;
+
     match db.upsert_rule(&new_rule) {
         Ok(_) => Json(ApiResponse {
             success: true,
@@ -93,14 +122,18 @@ pub async fn approve_rule(
     State(state): State<AppState>,
     Path(id): Path<i64>,
 ) -> Json<ApiResponse<()>> {
-    let db = state.db.lock().await;
-    match db.update_rule_status(id, "approved") {
-        Ok(_) => Json(ApiResponse {
-            success: true,
-            data: None,
-            message: Some("Rule approved".to_string()),
-            error: None,
-        }),
+    let result = state.db.lock().await.update_rule_status(id, "approved");
+
+    match result {
+        Ok(_) => {
+            reload_engine(&state).await;
+            Json(ApiResponse {
+                success: true,
+                data: None,
+                message: Some("Rule approved".to_string()),
+                error: None,
+            })
+        }
         Err(e) => Json(ApiResponse {
             success: false,
             data: None,
@@ -115,14 +148,18 @@ pub async fn reject_rule(
     State(state): State<AppState>,
     Path(id): Path<i64>,
 ) -> Json<ApiResponse<()>> {
-    let db = state.db.lock().await;
-    match db.update_rule_status(id, "rejected") {
-        Ok(_) => Json(ApiResponse {
-            success: true,
-            data: None,
-            message: Some("Rule rejected".to_string()),
-            error: None,
-        }),
+    let result = state.db.lock().await.update_rule_status(id, "rejected");
+
+    match result {
+        Ok(_) => {
+            reload_engine(&state).await;
+            Json(ApiResponse {
+                success: true,
+                data: None,
+                message: Some("Rule rejected".to_string()),
+                error: None,
+            })
+        }
         Err(e) => Json(ApiResponse {
             success: false,
             data: None,
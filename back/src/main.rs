@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -11,6 +11,7 @@ mod validation;
 mod config;
 mod auth;
 
+use cleaner::CleanerEngine;
 use cleaner::database::Database;
 use cleaner::database::admin::create_first_admin_if_needed;
 use crate::routes::create_router;
@@ -18,10 +19,12 @@ use crate::config::Config;
 
 
 
-// Shared database state
+// Shared database state and the cleaner engine built from its approved rules
 #[derive(Clone)]
 pub struct AppState {
     db: Arc<Mutex<Database>>,
+    engine: Arc<RwLock<CleanerEngine>>,
+    jwt_secret: Arc<String>,
 }
 
 #[tokio::main]
@@ -48,13 +51,23 @@ async fn main() {
 
     create_first_admin_if_needed(db.conn());
 
+    // Build the cleaner engine once, it is rebuilt whenever a rule status changes
+    let engine = match db.get_approved_rules().and_then(CleanerEngine::from_rules) {
+        Ok(engine) => engine,
+        Err(e) => {
+            tracing::error!("Failed to load approved rules, using embedded rules: {}", e);
+            CleanerEngine::embedded().expect("Embedded domain rules must be valid YAML")
+        }
+    };
+
     let state = AppState {
         db: Arc::new(Mutex::new(db)),
+        engine: Arc::new(RwLock::new(engine)),
+        jwt_secret: Arc::new(config.jwt_secret.clone()),
     };
 
     // Create router
-    let app = create_router()
-        .with_state(state)
+    let app = create_router(state)
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
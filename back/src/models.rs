@@ -15,9 +15,48 @@ pub struct SubmissionRequest {
     pub domain: String,
     pub keys: Vec<String>,
     pub starts_with: Option<Vec<String>>,
+    pub ends_with: Option<Vec<String>>,
+    pub patterns: Option<Vec<String>>,
+    pub regexes: Option<Vec<String>>,
+    pub path_segments: Option<Vec<String>>,
+    pub path_suffixes: Option<Vec<String>>,
+    pub path_params: Option<Vec<String>>,
+    pub ignore_case: Option<bool>,
+    pub exceptions: Option<Vec<String>>,
+    pub groups: Option<Vec<String>>,
+    pub mode: Option<cleaner::config::RuleMode>,
+    pub keep: Option<Vec<String>>,
+    pub values: Option<Vec<cleaner::config::ValueRule>>,
     pub contributor: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CleanRequest {
+    pub url: String,
+    #[serde(flatten)]
+    pub options: CleanOptionsRequest,
+}
+
+/// Cleaning options accepted by the clean endpoints, the URL shape is preserved by default
+#[derive(Debug, Default, Deserialize)]
+pub struct CleanOptionsRequest {
+    pub profile: Option<cleaner::UrlProfile>,
+    /// Subdomains removed by the canonical profile, `www` and `m` if not set
+    pub strip_subdomains: Option<Vec<String>>,
+    pub strip_text_fragments: Option<bool>,
+    /// Host rewrites to apply (`"all"` or `{"named": [...]}`), none if not set
+    pub rewrites: Option<cleaner::Rewrites>,
+    /// URL the relative links are resolved against
+    pub base: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CleanTextRequest {
+    pub text: String,
+    #[serde(flatten)]
+    pub options: CleanOptionsRequest,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
@@ -1,4 +1,5 @@
 use axum::{
+    middleware,
     routing::{get, post, put},
     Router,
 };
@@ -6,15 +7,102 @@ use crate::{auth::*, handlers::*};
 
 
 
-pub fn create_router() -> Router<crate::AppState> {
+pub fn create_router(state: crate::AppState) -> Router {
+    // Reviewing and importing rules or creating admins needs the token of an admin login
+    let admin = Router::new()
+        .route("/api/rules/{id}/approve", put(approve_rule))
+        .route("/api/rules/{id}/reject", put(reject_rule))
+        .route("/api/admin/create", post(create_admin))
+        .route("/api/admin/import/clearurls", post(import_clearurls))
+        .route("/api/admin/import/adguard", post(import_adguard))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin));
+
     Router::new()
         .route("/health", get(health_check))
+        .route("/api/clean", post(clean))
+        .route("/api/clean/text", post(clean_text))
         .route("/api/rules", get(get_rules))
         .route("/api/submit", post(submit_rule))
-        .route("/api/rules/{id}/approve", put(approve_rule))
-        .route("/api/rules/{id}/reject", put(reject_rule))
         .route("/api/rules/approved", get(get_approved_rules))
         .route("/api/rules/pending", get(get_pending_rules))
-        .route("/api/admin/create", post(create_admin))
+        .route("/api/rules/export/{format}", get(export_rules))
         .route("/api/admin/login", post(login_admin))
-}
\ No newline at end of file
+        .merge(admin)
+        .with_state(state)
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{header, Method, Request, StatusCode};
+    use cleaner::CleanerEngine;
+    use cleaner::database::Database;
+    use tokio::sync::{Mutex, RwLock};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::AppState;
+
+    const SECRET: &[u8] = b"test-secret";
+
+    fn state() -> AppState {
+        AppState {
+            db: Arc::new(Mutex::new(Database::new(":memory:").unwrap())),
+            engine: Arc::new(RwLock::new(CleanerEngine::embedded().unwrap())),
+            jwt_secret: Arc::new(String::from_utf8(SECRET.to_vec()).unwrap()),
+        }
+    }
+
+    fn request(method: Method, path: &str, token: Option<&str>, body: &str) -> Request<Body> {
+        let request = Request::builder().method(method).uri(path).header(header::CONTENT_TYPE, "application/json");
+        let request = match token {
+            Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {token}")),
+            None => request,
+        };
+        request.body(Body::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn imports_require_an_admin_token() {
+        let state = state();
+        let app = create_router(state.clone());
+
+        // A token signed with another secret is as invalid as no token
+        let forged = create_jwt("admin".to_string(), b"secret").unwrap();
+        for path in ["/api/admin/import/clearurls?approve=true", "/api/admin/import/adguard?approve=true"] {
+            for token in [None, Some("not-a-token"), Some(forged.as_str())] {
+                let response = app.clone().oneshot(request(Method::POST, path, token, "$removeparam=gclid")).await.unwrap();
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            }
+        }
+        assert!(state.db.lock().await.get_all_rules().unwrap().is_empty());
+
+        let token = create_jwt("admin".to_string(), SECRET).unwrap();
+        let response = app.oneshot(request(Method::POST, "/api/admin/import/adguard", Some(&token), "$removeparam=gclid")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.db.lock().await.get_all_rules().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reviews_and_admin_accounts_require_an_admin_token() {
+        let app = create_router(state());
+        let admin = r#"{"username": "eve", "password": "hunter2"}"#;
+
+        for (method, path, body) in [
+            (Method::PUT, "/api/rules/1/approve", ""),
+            (Method::PUT, "/api/rules/1/reject", ""),
+            (Method::POST, "/api/admin/create", admin),
+        ] {
+            let response = app.clone().oneshot(request(method, path, None, body)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        let token = create_jwt("admin".to_string(), SECRET).unwrap();
+        let response = app.oneshot(request(Method::POST, "/api/admin/create", Some(&token), admin)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -3,9 +3,10 @@ use lazy_static::lazy_static;
 
 // Compiling a regex is expensive, so we do it once and reuse it everywhere
 lazy_static! {
-    static ref DOMAIN_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap();
+    static ref DOMAIN_REGEX: Regex = Regex::new(r"^(\*\.)?[a-zA-Z0-9.-]+\.([a-zA-Z]{2,}|\*)$").unwrap();
 }
 
+// Accepts `*.` for any subdomain and `.*` for any public suffix, as in `amazon.*` or `*.google.*`
 pub fn is_valid_domain(domain: &str) -> bool {
     DOMAIN_REGEX.is_match(domain)
 }
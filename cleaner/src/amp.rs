@@ -0,0 +1,152 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+
+use crate::parts::UrlParts;
+use crate::registry::matched_domain_labels;
+
+/// What an AMP rule does to a URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmpRuleKind {
+    /// An AMP cache or viewer embedding the original URL in its path after `value`:
+    /// `google.com/amp/s/example.com/page` or `example-com.cdn.ampproject.org/c/s/example.com/page`.
+    /// An `s/` right after the prefix means the original URL is https.
+    Cache,
+    /// A mobile subdomain label removed from the host: `m` in `en.m.wikipedia.org`
+    MobileHost,
+    /// A query parameter requesting the AMP variant: `?amp=1`
+    Param,
+    /// A path segment of the AMP variant: `/article/amp/`
+    PathSegment,
+    /// A suffix of the last path segment of the AMP variant: `/article.amp`
+    PathSuffix,
+}
+
+impl AmpRuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AmpRuleKind::Cache => "cache",
+            AmpRuleKind::MobileHost => "mobile_host",
+            AmpRuleKind::Param => "param",
+            AmpRuleKind::PathSegment => "path_segment",
+            AmpRuleKind::PathSuffix => "path_suffix",
+        }
+    }
+}
+
+impl FromStr for AmpRuleKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "cache" => Ok(AmpRuleKind::Cache),
+            "mobile_host" => Ok(AmpRuleKind::MobileHost),
+            "param" => Ok(AmpRuleKind::Param),
+            "path_segment" => Ok(AmpRuleKind::PathSegment),
+            "path_suffix" => Ok(AmpRuleKind::PathSuffix),
+            _ => Err(format!("Unknown AMP rule kind: {kind}")),
+        }
+    }
+}
+
+/// A rule turning an AMP or mobile URL into the canonical page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmpRule {
+    pub kind: AmpRuleKind,
+
+    /// Domain of the rule, subdomains included, or `default` for every domain
+    pub domain: String,
+
+    /// Path prefix, host label, parameter name, path segment or suffix, depending on the kind
+    pub value: String,
+}
+
+impl AmpRule {
+    /// Rewrite the URL if the rule applies to it. `source` is the URL as it was written,
+    /// the parts the rule doesn't touch are copied from it.
+    pub fn apply(&self, url: &Url, source: &str) -> Option<String> {
+        let parts = UrlParts::split(source);
+
+        match self.kind {
+            AmpRuleKind::Cache => self.unwrap_cache(url, &parts),
+            AmpRuleKind::MobileHost => self.remove_host_label(url, &parts),
+            AmpRuleKind::Param => {
+                let query = parts.query?;
+                let kept: Vec<&str> = query
+                    .split('&')
+                    .filter(|pair| form_urlencoded::parse(pair.as_bytes()).next().is_none_or(|(key, _)| key != self.value.as_str()))
+                    .collect();
+
+                if kept.len() == query.split('&').count() {
+                    return None;
+                }
+
+                let query = kept.join("&");
+                Some(UrlParts { query: Some(&query).filter(|query| !query.is_empty()).map(String::as_str), ..parts }.to_string())
+            }
+            AmpRuleKind::PathSegment => {
+                let segments: Vec<&str> = parts.path.strip_prefix('/')?.split('/').collect();
+                let kept: Vec<&str> = segments
+                    .iter()
+                    .copied()
+                    .filter(|segment| !segment.eq_ignore_ascii_case(&self.value))
+                    .collect();
+
+                if kept.len() == segments.len() {
+                    return None;
+                }
+
+                let path = format!("/{}", kept.join("/"));
+                Some(UrlParts { path: &path, ..parts }.to_string())
+            }
+            AmpRuleKind::PathSuffix => {
+                let path = parts.path.trim_end_matches('/');
+                let stripped = path.strip_suffix(self.value.as_str()).filter(|path| !path.ends_with('/'))?;
+
+                let path = format!("{stripped}{}", &parts.path[path.len()..]);
+                Some(UrlParts { path: &path, ..parts }.to_string())
+            }
+        }
+    }
+
+    /// Extract the original URL embedded in the path of an AMP cache
+    fn unwrap_cache(&self, url: &Url, parts: &UrlParts) -> Option<String> {
+        let embedded = parts.path.strip_prefix(self.value.as_str())?;
+        let mut destination = match embedded.strip_prefix("s/") {
+            Some(embedded) => format!("https://{embedded}"),
+            None => format!("http://{embedded}"),
+        };
+
+        if let Some(query) = parts.query {
+            destination.push('?');
+            destination.push_str(query);
+        }
+        if let Some(fragment) = parts.fragment {
+            destination.push('#');
+            destination.push_str(fragment);
+        }
+
+        Url::parse(&destination)
+            .ok()
+            .filter(|destination| destination.host_str().is_some_and(|host| host.contains('.')) && destination.host() != url.host())
+            .map(|_| destination)
+    }
+
+    /// Remove the mobile label from the subdomains of the host.
+    /// The rule domain may be a wildcard (`*.wikipedia.org`, `wikipedia.*`), the labels it matches are kept.
+    fn remove_host_label(&self, url: &Url, parts: &UrlParts) -> Option<String> {
+        let host = url.host_str()?;
+        let labels: Vec<&str> = host.split('.').collect();
+        let subdomains = labels.len().checked_sub(matched_domain_labels(&self.domain, host)?)?;
+
+        let index = labels[..subdomains].iter().position(|label| *label == self.value)?;
+        let kept: Vec<&str> = labels[..index].iter().chain(&labels[index + 1..]).copied().collect();
+        let new_host = kept.join(".");
+
+        let prefix = parts.prefix_with_host(host, &new_host)?;
+
+        Some(UrlParts { prefix: &prefix, ..*parts }.to_string())
+    }
+}
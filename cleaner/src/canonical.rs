@@ -0,0 +1,131 @@
+use url::Url;
+
+use crate::registry::is_public_suffix;
+
+/// Subdomains removed by the canonical profile unless configured otherwise
+pub const DEFAULT_STRIPPED_SUBDOMAINS: &[&str] = &["www", "m"];
+
+/// Rewrite a cleaned URL into its canonical form, so equivalent links compare equal:
+/// the configured leading subdomains are removed unless only a public suffix would be left (`www.co.uk`),
+/// parameters with an empty value are dropped, the remaining ones are sorted by name and percent-encoding is normalized.
+/// Valueless flags (`?download`, `?print`) change what the page does and are kept.
+/// The scheme and host are already lowercased and default ports dropped by `Url` itself.
+pub(crate) fn canonicalize(url: &mut Url, strip_subdomains: &[String]) {
+    let host = url.host_str().map(str::to_string);
+    if let Some(host) = host {
+        let stripped = strip_subdomains
+            .iter()
+            .filter_map(|subdomain| host.strip_prefix(subdomain.as_str())?.strip_prefix('.'))
+            .find(|rest| rest.contains('.') && !is_public_suffix(rest));
+
+        if let Some(stripped) = stripped {
+            // Can't fail, the remaining labels were part of a valid host
+            let _ = url.set_host(Some(stripped));
+        }
+    }
+
+    let path = normalize_percent_encoding(url.path());
+    url.set_path(&path);
+
+    let query = url.query().map(canonical_query);
+    url.set_query(query.as_deref().filter(|query| !query.is_empty()));
+
+    let fragment = url.fragment().map(normalize_percent_encoding);
+    url.set_fragment(fragment.as_deref().filter(|fragment| !fragment.is_empty()));
+}
+
+/// Drop the parameters with an empty value and sort the others by name, keeping the order of repeated names
+fn canonical_query(query: &str) -> String {
+    let mut pairs: Vec<(String, Option<String>)> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (pair, None),
+        })
+        .filter(|(key, value)| !key.is_empty() && value.is_none_or(|value| !value.is_empty()))
+        .map(|(key, value)| (normalize_percent_encoding(key), value.map(normalize_percent_encoding)))
+        .collect();
+
+    pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    pairs
+        .iter()
+        .map(|(key, value)| match value {
+            Some(value) => format!("{key}={value}"),
+            None => key.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Decode the percent-encoded unreserved characters (`%7E` becomes `~`)
+/// and uppercase the hex digits of the other escapes (`%2f` becomes `%2F`)
+fn normalize_percent_encoding(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut output = String::with_capacity(input.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escape = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| bytes[index] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escape {
+            Some(byte) if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') => {
+                output.push(byte as char);
+                index += 3;
+            }
+            Some(byte) => {
+                output.push_str(&format!("%{byte:02X}"));
+                index += 3;
+            }
+            None => {
+                let c = input[index..].chars().next().unwrap_or_default();
+                output.push(c);
+                index += c.len_utf8();
+            }
+        }
+    }
+
+    output
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(input: &str) -> String {
+        let mut url = Url::parse(input).unwrap();
+        let subdomains: Vec<String> = DEFAULT_STRIPPED_SUBDOMAINS.iter().map(|s| s.to_string()).collect();
+        canonicalize(&mut url, &subdomains);
+        url.to_string()
+    }
+
+    #[test]
+    fn canonicalizes_host_and_port() {
+        assert_eq!(canonical("HTTPS://WWW.Example.COM:443/a"), "https://example.com/a");
+        assert_eq!(canonical("http://m.example.com:8080/"), "http://example.com:8080/");
+
+        // A bare `www.com` is a domain, not a subdomain, and so are the domains right under a public suffix
+        assert_eq!(canonical("https://www.com/"), "https://www.com/");
+        assert_eq!(canonical("https://www.co.uk/"), "https://www.co.uk/");
+        assert_eq!(canonical("https://m.github.io/"), "https://m.github.io/");
+        assert_eq!(canonical("https://www.example.co.uk/"), "https://example.co.uk/");
+    }
+
+    #[test]
+    fn sorts_and_strips_query_params() {
+        assert_eq!(canonical("https://example.com/?b=2&a=&flag&a=1&c=3&a=0"), "https://example.com/?a=1&a=0&b=2&c=3&flag");
+        assert_eq!(canonical("https://example.com/file?print&download"), "https://example.com/file?download&print");
+        assert_eq!(canonical("https://example.com/?empty="), "https://example.com/");
+    }
+
+    #[test]
+    fn normalizes_percent_encoding() {
+        assert_eq!(normalize_percent_encoding("%7euser/%2f%41%zz"), "~user/%2FA%zz");
+        assert_eq!(canonical("https://example.com/%7Euser/caf%c3%a9?q=a%2fb"), "https://example.com/~user/caf%C3%A9?q=a%2Fb");
+    }
+}
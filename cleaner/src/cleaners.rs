@@ -1,28 +1,139 @@
-use std::collections::HashSet;
+use crate::matchers::{MatcherKind, MatcherSet, ParamMatcher, RuleOrigin};
 use crate::traits::UrlCleaner;
 
+/// Allowlist of a `keep_only` cleaner
+struct KeepOnly {
+    /// The only query parameters that are kept
+    kept: MatcherSet,
+
+    /// Rule reported for every other parameter
+    others: ParamMatcher,
+}
+
 pub struct DomainCleaner {
-    /// Query parameters that should be removed if they exactly match a key in this set
-    keys: HashSet<String>,
+    /// Query parameters that should be removed if they match one of these rules
+    /// (exact keys, prefixes, suffixes, globs or regular expressions)
+    matchers: MatcherSet,
+
+    /// Query parameters that must be kept even if another rule matches them
+    exceptions: MatcherSet,
+
+    /// Path segments that should be removed entirely
+    path_segments: MatcherSet,
 
-    /// Query parameters that should be removed if they start with a key in this set
-    starts_with: HashSet<String>,
+    /// Tracking tails (`_ref=sr_1_1`) that should be cut from the end of path segments
+    path_suffixes: MatcherSet,
+
+    /// Matrix parameters (`;jsessionid=...`) that should be removed from path segments
+    path_params: MatcherSet,
+
+    /// In `keep_only` mode, the only query parameters that are kept
+    keep_only: Option<KeepOnly>,
 }
 
 impl DomainCleaner {
 
-    /// Create a new domain-specific cleaner
+    /// Create a new domain-specific cleaner from exact keys and prefixes
     pub fn new(keys: &[&str], starts_with: &[&str]) -> Self {
+        let matchers = keys
+            .iter()
+            .map(|key| ParamMatcher::new(MatcherKind::Key, key, false))
+            .chain(starts_with.iter().map(|prefix| ParamMatcher::new(MatcherKind::Prefix, prefix, false)))
+            .collect();
+
+        Self::from_matchers(matchers)
+    }
+
+    /// Create a new domain-specific cleaner from any kind of matchers, compiled once
+    pub fn from_matchers(matchers: Vec<ParamMatcher>) -> Self {
         Self {
-            keys: keys.iter().map(|k| k.to_string()).collect(),
-            starts_with: starts_with.iter().map(|k| k.to_string()).collect(),
+            matchers: MatcherSet::new(matchers),
+            exceptions: MatcherSet::new(Vec::new()),
+            path_segments: MatcherSet::new(Vec::new()),
+            path_suffixes: MatcherSet::new(Vec::new()),
+            path_params: MatcherSet::new(Vec::new()),
+            keep_only: None,
         }
     }
+
+    /// Remove path segments matching these rules, and matrix parameters matching those
+    pub fn with_path_rules(mut self, segments: Vec<ParamMatcher>, params: Vec<ParamMatcher>) -> Self {
+        self.path_segments = MatcherSet::new(segments);
+        self.path_params = MatcherSet::new(params);
+        self
+    }
+
+    /// Cut the tails of path segments matching these rules
+    pub fn with_path_suffixes(mut self, suffixes: Vec<ParamMatcher>) -> Self {
+        self.path_suffixes = MatcherSet::new(suffixes);
+        self
+    }
+
+    /// Keep the given query parameters even if a rule matches them, in any letter case if `ignore_case` is set
+    pub fn with_exceptions(mut self, exceptions: &[&str], ignore_case: bool) -> Self {
+        self.exceptions = MatcherSet::new(
+            exceptions
+                .iter()
+                .map(|exception| ParamMatcher::new(MatcherKind::Key, exception, ignore_case))
+                .collect(),
+        );
+        self
+    }
+
+    /// Remove every query parameter except the ones matching these rules (and the exceptions).
+    /// `origin` is the layer that switched the cleaner to `keep_only` mode.
+    pub fn keep_only(mut self, keep: Vec<ParamMatcher>, origin: Option<RuleOrigin>) -> Self {
+        let mut others = ParamMatcher::new(MatcherKind::Glob, "*", false);
+        others.origin = origin;
+
+        self.keep_only = Some(KeepOnly {
+            kept: MatcherSet::new(keep),
+            others,
+        });
+        self
+    }
+
+    /// All the matchers of this cleaner, in the order they are tried
+    pub fn matchers(&self) -> &[ParamMatcher] {
+        self.matchers.matchers()
+    }
 }
 
 impl UrlCleaner for DomainCleaner {
-    fn should_remove(&self, key: &str) -> bool {
-        self.keys.contains(key) || self.starts_with.iter().any(|prefix| key.starts_with(prefix))
+    fn matching_rule(&self, key: &str) -> Option<&ParamMatcher> {
+        if self.exceptions.find(key).is_some() {
+            return None;
+        }
+
+        if let Some(keep_only) = &self.keep_only {
+            return keep_only.kept.find(key).is_none().then_some(&keep_only.others);
+        }
+
+        self.matchers.find(key)
+    }
+
+    fn matching_rule_with_value(&self, key: &str, value: &str) -> Option<&ParamMatcher> {
+        if self.exceptions.find(key).is_some() {
+            return None;
+        }
+
+        if let Some(keep_only) = &self.keep_only {
+            return keep_only.kept.find(key).is_none().then_some(&keep_only.others);
+        }
+
+        self.matchers.find_with_value(key, value)
+    }
+
+    fn matching_segment_rule(&self, segment: &str) -> Option<&ParamMatcher> {
+        self.path_segments.find(segment)
+    }
+
+    fn matching_segment_suffix_rule(&self, suffix: &str) -> Option<&ParamMatcher> {
+        self.path_suffixes.find(suffix)
+    }
+
+    fn matching_path_param_rule(&self, name: &str) -> Option<&ParamMatcher> {
+        self.path_params.find(name)
     }
 }
 
@@ -31,6 +142,7 @@ impl UrlCleaner for DomainCleaner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::matchers::{ValueCondition, ValueMatch};
 
     #[test]
     fn removes_exact_keys() {
@@ -61,4 +173,87 @@ mod tests {
 
         assert!(!cleaner.should_remove("keep"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn exceptions_override_keys_and_prefixes() {
+        let cleaner = DomainCleaner::new(&["ref"], &["utm_"]).with_exceptions(&["ref", "utm_id"], false);
+
+        assert!(cleaner.should_remove("utm_source"));
+
+        assert!(!cleaner.should_remove("ref"));
+        assert!(!cleaner.should_remove("utm_id"));
+    }
+
+    #[test]
+    fn exceptions_ignore_case_like_the_rules() {
+        let cleaner = DomainCleaner::from_matchers(vec![ParamMatcher::new(MatcherKind::Key, "ref", true)]).with_exceptions(&["ref"], true);
+
+        assert!(!cleaner.should_remove("ref"));
+        assert!(!cleaner.should_remove("REF"));
+        assert!(!cleaner.should_remove("Ref"));
+    }
+
+    #[test]
+    fn removes_path_segments_and_matrix_params() {
+        let cleaner = DomainCleaner::new(&[], &[]).with_path_rules(
+            vec![ParamMatcher::new(MatcherKind::Glob, "ref=*", false)],
+            vec![ParamMatcher::new(MatcherKind::Key, "jsessionid", true)],
+        );
+
+        assert!(cleaner.matching_segment_rule("ref=sr_1_1").is_some());
+        assert!(cleaner.matching_path_param_rule("JSESSIONID").is_some());
+
+        assert!(cleaner.matching_segment_rule("B000123").is_none());
+        assert!(cleaner.matching_path_param_rule("lang").is_none());
+    }
+
+    #[test]
+    fn removes_suffix_glob_and_regex_matches() {
+        let cleaner = DomainCleaner::from_matchers(vec![
+            ParamMatcher::new(MatcherKind::Suffix, "_hsenc", false),
+            ParamMatcher::new(MatcherKind::Glob, "mc_[ce]id", false),
+            ParamMatcher::new(MatcherKind::Regex, "(?:oly|vero)_\\w+", false),
+            ParamMatcher::new(MatcherKind::Key, "utm_source", true),
+        ]);
+
+        assert!(cleaner.should_remove("__hsenc"));
+        assert!(cleaner.should_remove("mc_eid"));
+        assert!(cleaner.should_remove("vero_id"));
+        assert!(cleaner.should_remove("UTM_Source"));
+
+        assert!(!cleaner.should_remove("mc_id"));
+        assert!(!cleaner.should_remove("keep"));
+    }
+
+    #[test]
+    fn value_conditions_only_match_some_values() {
+        let cleaner = DomainCleaner::from_matchers(vec![
+            ParamMatcher::new(MatcherKind::Key, "ref", false).with_value(ValueCondition::new(ValueMatch::Exact, "twitter")),
+            ParamMatcher::new(MatcherKind::Key, "source", false).with_value(ValueCondition::new(ValueMatch::Prefix, "share")),
+            ParamMatcher::new(MatcherKind::Prefix, "utm_", false).with_value(ValueCondition::new(ValueMatch::Regex, "(?i)news.*")),
+        ]);
+
+        assert!(cleaner.should_remove_with_value("ref", "twitter"));
+        assert!(cleaner.should_remove_with_value("source", "share_sheet"));
+        assert!(cleaner.should_remove_with_value("utm_source", "Newsletter"));
+
+        assert!(!cleaner.should_remove_with_value("ref", "refs/heads/main"));
+        assert!(!cleaner.should_remove_with_value("utm_source", "rss"));
+
+        // Conditional rules never match without the value
+        assert!(!cleaner.should_remove("ref"));
+    }
+
+    #[test]
+    fn keep_only_removes_everything_but_the_allowlist() {
+        let cleaner = DomainCleaner::new(&[], &[])
+            .with_exceptions(&["lang"], false)
+            .keep_only(vec![ParamMatcher::new(MatcherKind::Key, "v", false)], None);
+
+        assert!(!cleaner.should_remove("v"));
+        assert!(!cleaner.should_remove("lang"));
+
+        assert!(cleaner.should_remove("V"));
+        assert!(cleaner.should_remove("si"));
+    }
+}
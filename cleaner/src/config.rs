@@ -1,62 +1,466 @@
-use std::collections::HashMap;
-use serde::Deserialize;
-use std::error::Error;
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
+use crate::amp::AmpRule;
 use crate::cleaners::DomainCleaner;
+use crate::matchers::{MatcherKind, ParamMatcher, RuleLayer, RuleOrigin, ValueCondition, ValueMatch};
+use crate::redirects::RedirectRule;
+use crate::rewrites::RewriteRule;
+use crate::registry::{Registry, DEFAULT_DOMAIN};
+#[cfg(feature = "database")]
 use crate::database::Database;
-use crate::database::rules::Result as DbResult;
+use crate::error::CleanError;
+use crate::export::{RuleBundle, BUNDLE_VERSION};
 
 pub const DOMAIN_RULES_YAML: &str = include_str!("config/domain_rules.yaml");
 
 
-/// Configuration for a domain's URL cleaning rules
-#[derive(Debug, Deserialize, Clone)]
-pub struct RuleConfig {
+/// How a domain cleaner treats the query parameters its rules don't mention
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMode {
+    /// Remove the parameters matched by the rules, keep everything else
+    #[default]
+    Remove,
+    /// Remove every parameter except the ones listed in `keep`
+    KeepOnly,
+}
+
+impl RuleMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleMode::Remove => "remove",
+            RuleMode::KeepOnly => "keep_only",
+        }
+    }
+
+    fn is_remove(&self) -> bool {
+        *self == RuleMode::Remove
+    }
+}
+
+impl FromStr for RuleMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "remove" => Ok(RuleMode::Remove),
+            "keep_only" => Ok(RuleMode::KeepOnly),
+            _ => Err(format!("Unknown rule mode: {mode}")),
+        }
+    }
+}
+
+/// A query parameter removed only when its value meets a condition (`ref=twitter`)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ValueRule {
+    pub key: String,
+
+    /// How `value` is compared with the decoded parameter value
     #[serde(default)]
+    pub kind: ValueMatch,
+
+    pub value: String,
+}
+
+/// Configuration for one layer of URL cleaning rules (global, group or domain)
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub keys: Vec<String>,
 
-    #[serde(default)]
-    pub starts_with: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub starts_with: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ends_with: Vec<String>,
+
+    /// Globs such as `mc_[ce]id` or `*_hsenc`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+
+    /// Regular expressions matching the whole parameter name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regexes: Vec<String>,
+
+    /// Globs of path segments to drop entirely, such as Amazon's `ref=*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_segments: Vec<String>,
+
+    /// Globs of tracking tails cut from the end of path segments, such as `_ref=*` in `/title_ref=sr_1_1`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_suffixes: Vec<String>,
+
+    /// Parameters removed only for some values, such as `ref=twitter`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<ValueRule>,
+
+    /// Matrix parameters to strip from path segments, such as `jsessionid` in `/page;jsessionid=...`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_params: Vec<String>,
+
+    /// Match the parameter names of this layer case-insensitively (`UTM_Source`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ignore_case: bool,
+
+    /// Parameters this layer keeps even if a previous layer removes them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exceptions: Vec<String>,
+
+    /// Group layers applied between the global layer and this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+
+    /// Whether the rules list the parameters to remove or, with `keep_only`, the ones to keep
+    #[serde(default, skip_serializing_if = "RuleMode::is_remove")]
+    pub mode: RuleMode,
+
+    /// Parameters kept by a `keep_only` cleaner, every other one is removed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keep: Vec<String>,
+}
+
+impl RuleConfig {
+    /// Add the rules of another layer definition to this one
+    pub fn extend(&mut self, other: &RuleConfig) {
+        self.keys.extend(other.keys.iter().cloned());
+        self.starts_with.extend(other.starts_with.iter().cloned());
+        self.ends_with.extend(other.ends_with.iter().cloned());
+        self.patterns.extend(other.patterns.iter().cloned());
+        self.regexes.extend(other.regexes.iter().cloned());
+        self.values.extend(other.values.iter().cloned());
+        self.path_segments.extend(other.path_segments.iter().cloned());
+        self.path_suffixes.extend(other.path_suffixes.iter().cloned());
+        self.path_params.extend(other.path_params.iter().cloned());
+        self.ignore_case |= other.ignore_case;
+        self.exceptions.extend(other.exceptions.iter().cloned());
+        self.groups.extend(other.groups.iter().cloned());
+        if other.mode == RuleMode::KeepOnly {
+            self.mode = RuleMode::KeepOnly;
+        }
+        self.keep.extend(other.keep.iter().cloned());
+    }
+
+    /// Get the parameter matchers defined by this layer
+    pub fn matchers(&self) -> Vec<ParamMatcher> {
+        let kinds = [
+            (MatcherKind::Key, &self.keys),
+            (MatcherKind::Prefix, &self.starts_with),
+            (MatcherKind::Suffix, &self.ends_with),
+            (MatcherKind::Glob, &self.patterns),
+            (MatcherKind::Regex, &self.regexes),
+        ];
+
+        kinds
+            .into_iter()
+            .flat_map(|(kind, patterns)| patterns.iter().map(move |pattern| (kind, pattern)))
+            .map(|(kind, pattern)| ParamMatcher::new(kind, pattern, self.ignore_case))
+            .chain(self.values.iter().map(|rule| {
+                ParamMatcher::new(MatcherKind::Key, &rule.key, self.ignore_case).with_value(ValueCondition::new(rule.kind, &rule.value))
+            }))
+            .collect()
+    }
+
+    /// Get the path segment matchers defined by this layer
+    pub fn path_segment_matchers(&self) -> Vec<ParamMatcher> {
+        self.path_segments
+            .iter()
+            .map(|pattern| ParamMatcher::new(MatcherKind::Glob, pattern, self.ignore_case))
+            .collect()
+    }
+
+    /// Get the path segment suffix matchers defined by this layer
+    pub fn path_suffix_matchers(&self) -> Vec<ParamMatcher> {
+        self.path_suffixes
+            .iter()
+            .map(|pattern| ParamMatcher::new(MatcherKind::Glob, pattern, self.ignore_case))
+            .collect()
+    }
+
+    /// Get the matchers of the parameters kept in `keep_only` mode
+    pub fn keep_matchers(&self) -> Vec<ParamMatcher> {
+        self.keep
+            .iter()
+            .map(|name| ParamMatcher::new(MatcherKind::Key, name, self.ignore_case))
+            .collect()
+    }
+
+    /// Get the matrix parameter matchers defined by this layer
+    pub fn path_param_matchers(&self) -> Vec<ParamMatcher> {
+        self.path_params
+            .iter()
+            .map(|name| ParamMatcher::new(MatcherKind::Key, name, self.ignore_case))
+            .collect()
+    }
 }
 
-/// Top level configuration structure with defaults and domain-specific rules
-#[derive(Debug, Deserialize)]
+/// Top level configuration structure with the global layer, group layers and domain-specific rules
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConfigFile {
     pub defaults: RuleConfig,
-    pub domains: HashMap<String, RuleConfig>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, RuleConfig>,
+
+    pub domains: BTreeMap<String, RuleConfig>,
+
+    /// Tracker redirect wrappers whose destination URL is extracted and cleaned
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<RedirectRule>,
+
+    /// AMP caches and AMP or mobile variants turned into the canonical page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amp: Vec<AmpRule>,
+
+    /// Host rewrites to privacy front-ends, only applied when a cleaning call selects them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rewrites: Vec<RewriteRule>,
 }
 
-/// Load the registry from the embedded config file
-pub fn load_registry_from_file() -> Result<HashMap<String, DomainCleaner>, Box<dyn Error>> {
-    let config: ConfigFile = serde_yaml::from_str(DOMAIN_RULES_YAML)?;
+impl ConfigFile {
+    /// Compose every domain with the global layer and its group layers into a registry,
+    /// along with the redirect, AMP and rewrite rules. The global layer alone becomes the `default` cleaner.
+    pub fn build_registry(&self) -> Registry {
+        // A `default` domain entry is part of the global layer
+        let mut global = self.defaults.clone();
+        if let Some(default) = self.domains.get(DEFAULT_DOMAIN) {
+            global.extend(default);
+        }
+
+        let global = (RuleOrigin::new(RuleLayer::Global, DEFAULT_DOMAIN), &global);
+
+        let mut registry = Registry::new();
+        registry.insert(DEFAULT_DOMAIN, compose_layers(std::slice::from_ref(&global)));
+
+        for (domain, rule) in &self.domains {
+            if domain == DEFAULT_DOMAIN {
+                continue;
+            }
 
-    let mut registry = HashMap::new();
+            let mut layers = vec![global.clone()];
+            for group in &rule.groups {
+                match self.groups.get(group) {
+                    Some(layer) => layers.push((RuleOrigin::new(RuleLayer::Group, group), layer)),
+                    None => tracing::warn!("Domain {} uses unknown rule group {}", domain, group),
+                }
+            }
+            layers.push((RuleOrigin::new(RuleLayer::Domain, domain), rule));
 
-    for (domain, rule) in config.domains {
-        // Merge with defaults: use domain-specific values if provided, otherwise use defaults
-        let merged_keys = if rule.keys.is_empty() {
-            config.defaults.keys.clone()
-        } else {
-            rule.keys
-        };
-        
-        let merged_starts_with = rule.starts_with
-            .or_else(|| config.defaults.starts_with.clone())
-            .unwrap_or_else(|| vec!["utm_".to_string()]);
+            registry.insert(domain, compose_layers(&layers));
+        }
+
+        for redirect in &self.redirects {
+            registry.add_redirect(redirect.clone());
+        }
+
+        for rule in &self.amp {
+            registry.add_amp_rule(rule.clone());
+        }
 
-        let cleaner = DomainCleaner::new(
-            &merged_keys.iter().map(String::as_str).collect::<Vec<_>>(),
-            &merged_starts_with.iter().map(String::as_str).collect::<Vec<_>>(),
-        );
+        for rule in &self.rewrites {
+            registry.add_rewrite(rule.clone());
+        }
 
-        registry.insert(domain, cleaner);
+        registry
     }
+}
+
+/// Layer of a rule that applies to the domain it names (and its subdomains)
+pub const LAYER_DOMAIN: &str = "domain";
 
-    Ok(registry)
+/// Layer of a rule that defines a group, applied to the domains listing it in `groups`
+pub const LAYER_GROUP: &str = "group";
+
+/// A layer of the rules as it is submitted, reviewed and stored: a domain or group layer
+/// with its contributors and review status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainRule {
+    pub id: Option<i32>,
+    pub domain: String,
+    #[serde(default = "default_layer")]
+    pub layer: String, // "domain" or "group"
+    pub keys: Vec<String>,
+    pub starts_with: Vec<String>,
+    #[serde(default)]
+    pub ends_with: Vec<String>,
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub regexes: Vec<String>,
+    /// Parameters removed only for some values
+    #[serde(default)]
+    pub values: Vec<ValueRule>,
+    #[serde(default)]
+    pub path_segments: Vec<String>,
+    #[serde(default)]
+    pub path_suffixes: Vec<String>,
+    #[serde(default)]
+    pub path_params: Vec<String>,
+    #[serde(default)]
+    pub ignore_case: bool,
+    #[serde(default)]
+    pub exceptions: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// `remove` drops the matched parameters, `keep_only` drops everything but `keep`
+    #[serde(default)]
+    pub mode: RuleMode,
+    #[serde(default)]
+    pub keep: Vec<String>,
+    pub contributors: Vec<String>,
+    pub status: String, // "pending", "approved", "rejected"
+}
+
+fn default_layer() -> String {
+    LAYER_DOMAIN.to_string()
+}
+
+impl Default for DomainRule {
+    fn default() -> Self {
+        Self {
+            id: None,
+            domain: String::new(),
+            layer: default_layer(),
+            keys: Vec::new(),
+            starts_with: Vec::new(),
+            ends_with: Vec::new(),
+            patterns: Vec::new(),
+            regexes: Vec::new(),
+            values: Vec::new(),
+            path_segments: Vec::new(),
+            path_suffixes: Vec::new(),
+            path_params: Vec::new(),
+            ignore_case: false,
+            exceptions: Vec::new(),
+            groups: Vec::new(),
+            mode: RuleMode::Remove,
+            keep: Vec::new(),
+            contributors: Vec::new(),
+            status: "pending".to_string(),
+        }
+    }
+}
+
+impl DomainRule {
+    /// Create a rule from a layer of the YAML configuration
+    pub fn from_config(domain: &str, layer: &str, config: &RuleConfig) -> Self {
+        Self {
+            domain: domain.to_string(),
+            layer: layer.to_string(),
+            keys: config.keys.clone(),
+            starts_with: config.starts_with.clone(),
+            ends_with: config.ends_with.clone(),
+            patterns: config.patterns.clone(),
+            regexes: config.regexes.clone(),
+            values: config.values.clone(),
+            path_segments: config.path_segments.clone(),
+            path_suffixes: config.path_suffixes.clone(),
+            path_params: config.path_params.clone(),
+            ignore_case: config.ignore_case,
+            exceptions: config.exceptions.clone(),
+            groups: config.groups.clone(),
+            mode: config.mode,
+            keep: config.keep.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Get the layer definition of this rule
+    pub fn to_config(&self) -> RuleConfig {
+        RuleConfig {
+            keys: self.keys.clone(),
+            starts_with: self.starts_with.clone(),
+            ends_with: self.ends_with.clone(),
+            patterns: self.patterns.clone(),
+            regexes: self.regexes.clone(),
+            values: self.values.clone(),
+            path_segments: self.path_segments.clone(),
+            path_suffixes: self.path_suffixes.clone(),
+            path_params: self.path_params.clone(),
+            ignore_case: self.ignore_case,
+            exceptions: self.exceptions.clone(),
+            groups: self.groups.clone(),
+            mode: self.mode,
+            keep: self.keep.clone(),
+        }
+    }
+}
+
+/// Merge rule layers, from the most generic to the most specific, into a single cleaner.
+/// A layer's exceptions keep parameters removed by previous layers,
+/// and a later layer listing one of them in `keys` removes it again.
+/// If any layer is in `keep_only` mode, the cleaner keeps only the parameters of every `keep` list.
+/// If any layer ignores the letter case, so do the exceptions, to protect every spelling its rules remove.
+/// Every rule remembers the layer it comes from, for the cleaning reports.
+pub fn compose_layers(layers: &[(RuleOrigin, &RuleConfig)]) -> DomainCleaner {
+    let mut keep_only = None;
+    let mut keep = Vec::new();
+    let mut matchers: Vec<ParamMatcher> = Vec::new();
+    let mut exceptions: Vec<&str> = Vec::new();
+    let mut path_segments = Vec::new();
+    let mut path_suffixes = Vec::new();
+    let mut path_params = Vec::new();
+
+    for (origin, layer) in layers {
+        let tag = |matchers: Vec<ParamMatcher>| matchers.into_iter().map(|matcher| matcher.with_origin(origin.clone()));
+
+        for key in &layer.keys {
+            exceptions.retain(|exception| exception != key);
+        }
+
+        matchers.extend(tag(layer.matchers()));
+        path_segments.extend(tag(layer.path_segment_matchers()));
+        path_suffixes.extend(tag(layer.path_suffix_matchers()));
+        path_params.extend(tag(layer.path_param_matchers()));
+        keep.extend(layer.keep_matchers());
+
+        if layer.mode == RuleMode::KeepOnly {
+            keep_only = Some(origin.clone());
+        }
+
+        for exception in &layer.exceptions {
+            matchers.retain(|matcher| matcher.kind != MatcherKind::Key || &matcher.pattern != exception);
+            exceptions.push(exception);
+        }
+    }
+
+    let ignore_case = layers.iter().any(|(_, layer)| layer.ignore_case);
+    let cleaner = DomainCleaner::from_matchers(matchers)
+        .with_exceptions(&exceptions, ignore_case)
+        .with_path_rules(path_segments, path_params)
+        .with_path_suffixes(path_suffixes);
+
+    match keep_only {
+        Some(origin) => cleaner.keep_only(keep, Some(origin)),
+        None => cleaner,
+    }
+}
+
+/// Load the registry from the embedded config file
+pub fn load_registry_from_file() -> Result<Registry, CleanError> {
+    load_registry_from_yaml(DOMAIN_RULES_YAML)
+}
+
+/// Load the registry from a YAML document in the `domain_rules.yaml` format
+pub fn load_registry_from_yaml(yaml: &str) -> Result<Registry, CleanError> {
+    let config: ConfigFile = serde_yaml::from_str(yaml)?;
+    Ok(config.build_registry())
+}
+
+/// Load registry from a JSON rule bundle, as written by the JSON export
+pub fn load_registry_from_bundle(json: &str) -> Result<Registry, CleanError> {
+    let bundle: RuleBundle = serde_json::from_str(json)?;
+    if bundle.version != BUNDLE_VERSION {
+        return Err(CleanError::BundleVersion(bundle.version));
+    }
+
+    Ok(bundle.rules.build_registry())
 }
 
 /// Load registry from database
-pub fn load_registry_from_database(_db_path: &str) -> DbResult<HashMap<String, DomainCleaner>> {
+#[cfg(feature = "database")]
+pub fn load_registry_from_database(_db_path: &str) -> Result<Registry, CleanError> {
     let db = Database::new(_db_path)?;
     
     match db.migrate_from_yaml() {
@@ -74,7 +478,8 @@ pub fn load_registry_from_database(_db_path: &str) -> DbResult<HashMap<String, D
 
 /// Fallback function to load registry from database or file
 /// If database is not found, load from file
-pub fn load_registry_with_fallback(_db_path: &str) -> Result<HashMap<String, DomainCleaner>, Box<dyn Error>> {
+#[cfg(feature = "database")]
+pub fn load_registry_with_fallback(_db_path: &str) -> Result<Registry, CleanError> {
     match load_registry_from_database(_db_path) {
         Ok(registry) => {
             println!("Successfully loaded registry from database");
@@ -85,4 +490,170 @@ pub fn load_registry_with_fallback(_db_path: &str) -> Result<HashMap<String, Dom
             load_registry_from_file()
         }
     }
-}
\ No newline at end of file
+}
+
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::UrlCleaner;
+
+    const LAYERED_YAML: &str = r#"
+defaults:
+  starts_with: ["utm_"]
+
+groups:
+  social:
+    keys: ["fbclid", "igshid"]
+
+domains:
+  example.com:
+    keys: ["ref"]
+  social.example:
+    groups: ["social"]
+    exceptions: ["utm_id"]
+  relaxed.example:
+    groups: ["social"]
+    exceptions: ["igshid"]
+"#;
+
+    #[test]
+    fn global_layer_applies_on_top_of_domain_keys() {
+        let registry = load_registry_from_yaml(LAYERED_YAML).unwrap();
+        let cleaner = registry.get("example.com").unwrap();
+
+        assert!(cleaner.should_remove("ref"));
+        assert!(cleaner.should_remove("utm_source"));
+    }
+
+    #[test]
+    fn group_layers_are_composed_between_global_and_domain() {
+        let registry = load_registry_from_yaml(LAYERED_YAML).unwrap();
+
+        let cleaner = registry.get("social.example").unwrap();
+        assert!(cleaner.should_remove("fbclid"));
+        assert!(cleaner.should_remove("utm_source"));
+        assert!(!cleaner.should_remove("utm_id"));
+
+        let cleaner = registry.get("relaxed.example").unwrap();
+        assert!(cleaner.should_remove("fbclid"));
+        assert!(!cleaner.should_remove("igshid"));
+    }
+
+    #[test]
+    fn later_layers_can_remove_an_exempted_key_again() {
+        let global = RuleConfig { exceptions: vec!["ref".to_string()], ..Default::default() };
+        let domain = RuleConfig { keys: vec!["ref".to_string()], ..Default::default() };
+        let global = (RuleOrigin::new(RuleLayer::Global, DEFAULT_DOMAIN), &global);
+        let domain = (RuleOrigin::new(RuleLayer::Domain, "example.com"), &domain);
+
+        assert!(!compose_layers(std::slice::from_ref(&global)).should_remove("ref"));
+
+        let cleaner = compose_layers(&[global, domain]);
+        let origin = cleaner.matching_rule("ref").and_then(|rule| rule.origin.clone());
+        assert_eq!(origin, Some(RuleOrigin::new(RuleLayer::Domain, "example.com")));
+    }
+
+    #[test]
+    fn layers_keep_their_own_case_sensitivity() {
+        let yaml = r#"
+defaults:
+  starts_with: ["utm_"]
+  ignore_case: true
+domains:
+  example.com:
+    keys: ["ref"]
+    ends_with: ["_hsenc"]
+    patterns: ["mc_[ce]id"]
+    regexes: ["oly_(anon|enc)_id"]
+"#;
+        let registry = load_registry_from_yaml(yaml).unwrap();
+        let cleaner = registry.get("example.com").unwrap();
+
+        assert!(cleaner.should_remove("UTM_Source"));
+        assert!(cleaner.should_remove("__hsenc"));
+        assert!(cleaner.should_remove("mc_cid"));
+        assert!(cleaner.should_remove("oly_enc_id"));
+
+        assert!(!cleaner.should_remove("REF"));
+    }
+
+    #[test]
+    fn exceptions_protect_every_spelling_of_case_insensitive_rules() {
+        let yaml = r#"
+defaults:
+  starts_with: ["utm_"]
+  keys: ["ref"]
+  ignore_case: true
+domains:
+  example.com:
+    exceptions: ["ref", "utm_id"]
+"#;
+        let registry = load_registry_from_yaml(yaml).unwrap();
+        let cleaner = registry.get("example.com").unwrap();
+
+        assert!(!cleaner.should_remove("REF"));
+        assert!(!cleaner.should_remove("Ref"));
+        assert!(!cleaner.should_remove("UTM_Id"));
+        assert!(cleaner.should_remove("UTM_Source"));
+    }
+
+    #[test]
+    fn value_rules_only_remove_some_values() {
+        let yaml = r#"
+defaults:
+  keys: ["gclid"]
+domains:
+  example.com:
+    values:
+      - { key: ref, value: twitter }
+      - { key: source, kind: prefix, value: share }
+"#;
+        let registry = load_registry_from_yaml(yaml).unwrap();
+        let cleaner = registry.get("example.com").unwrap();
+
+        assert!(cleaner.should_remove_with_value("ref", "twitter"));
+        assert!(cleaner.should_remove_with_value("source", "share_sheet"));
+        assert!(cleaner.should_remove_with_value("gclid", "abc"));
+
+        assert!(!cleaner.should_remove_with_value("ref", "refs/heads/main"));
+    }
+
+    #[test]
+    fn keep_only_layers_remove_everything_else() {
+        let yaml = r#"
+defaults:
+  starts_with: ["utm_"]
+groups:
+  video:
+    keep: ["t"]
+domains:
+  youtube.com:
+    mode: keep_only
+    groups: ["video"]
+    keep: ["v", "list", "index"]
+  amazon.com:
+    mode: keep_only
+  example.com:
+    keep: ["id"]
+"#;
+        let registry = load_registry_from_yaml(yaml).unwrap();
+
+        let cleaner = registry.get("www.youtube.com").unwrap();
+        for key in ["v", "t", "list", "index"] {
+            assert!(!cleaner.should_remove(key), "{key}");
+        }
+        assert!(cleaner.should_remove("si"));
+        assert!(cleaner.should_remove("pp"));
+
+        let cleaner = registry.get("amazon.com").unwrap();
+        assert!(cleaner.should_remove("keywords"));
+
+        // `keep` alone doesn't switch the mode
+        let cleaner = registry.get("example.com").unwrap();
+        assert!(!cleaner.should_remove("other"));
+        assert!(cleaner.should_remove("utm_source"));
+    }
+}
@@ -0,0 +1,104 @@
+use rusqlite::{Connection, Result as SqliteResult, params};
+use crate::amp::AmpRule;
+use crate::config::ConfigFile;
+use super::rules::Result;
+
+/// Initialize the AMP rules schema
+pub fn init_schema(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS amp_rules (
+            id INTEGER PRIMARY KEY,
+            kind TEXT NOT NULL,
+            domain TEXT NOT NULL,
+            value TEXT NOT NULL,
+            contributor TEXT,
+            status TEXT DEFAULT 'pending'
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_amp_rules_status ON amp_rules(status)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Insert a new AMP rule into the database
+pub fn insert_amp_rule(conn: &Connection, rule: &AmpRule, contributors: &[String], status: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO amp_rules (kind, domain, value, contributor, status) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            rule.kind.as_str(),
+            rule.domain,
+            rule.value,
+            serde_json::to_string(contributors)?,
+            status,
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Get approved AMP rules
+pub fn get_approved_amp_rules(conn: &Connection) -> Result<Vec<AmpRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT kind, domain, value FROM amp_rules WHERE status = 'approved' ORDER BY id"
+    )?;
+
+    let rules = stmt.query_map([], |row| {
+        Ok(AmpRule {
+            kind: row
+                .get::<_, String>(0)?
+                .parse()
+                .map_err(rusqlite::Error::InvalidParameterName)?,
+            domain: row.get(1)?,
+            value: row.get(2)?,
+        })
+    })?.collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(rules)
+}
+
+/// Seed the AMP rules from YAML if the table is empty
+pub fn migrate_from_yaml(conn: &Connection) -> Result<()> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM amp_rules",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if count > 0 {
+        tracing::info!("Database already has {} AMP rules, skipping migration", count);
+        return Ok(());
+    }
+
+    let config: ConfigFile = serde_yaml::from_str(crate::config::DOMAIN_RULES_YAML)?;
+    let contributors = vec!["system".to_string()];
+
+    for rule in &config.amp {
+        insert_amp_rule(conn, rule, &contributors, "approved")?;
+    }
+
+    tracing::info!("Successfully migrated {} AMP rules from YAML to database", config.amp.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_amp_rules_once() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        init_schema(&conn).expect("Should initialize schema");
+
+        migrate_from_yaml(&conn).expect("Should migrate AMP rules");
+        migrate_from_yaml(&conn).expect("Should skip the second migration");
+
+        let rules = get_approved_amp_rules(&conn).expect("Should get AMP rules");
+        let config: ConfigFile = serde_yaml::from_str(crate::config::DOMAIN_RULES_YAML).unwrap();
+        assert_eq!(rules, config.amp);
+    }
+}
@@ -2,10 +2,17 @@ use rusqlite::Connection;
 use thiserror::Error;
 
 pub mod rules;
+pub mod redirects;
+pub mod amp;
+pub mod rewrites;
+#[cfg(feature = "admin")]
 pub mod admin;
 
 // Re-export commonly used types
-pub use rules::{DomainRule, DatabaseError, Result};
+pub use rules::{DomainRule, DatabaseError};
+
+/// Result type of the `Database` methods
+pub type Result<T> = std::result::Result<T, crate::error::CleanError>;
 
 #[derive(Debug, Error)]
 pub enum InitError {
@@ -29,8 +36,12 @@ impl Database {
     }
 
     /// Initialize the schema for all modules
-    fn init_schema(&self) -> Result<()> {
+    fn init_schema(&self) -> rules::Result<()> {
         rules::init_schema(&self.conn)?;
+        redirects::init_schema(&self.conn)?;
+        amp::init_schema(&self.conn)?;
+        rewrites::init_schema(&self.conn)?;
+        #[cfg(feature = "admin")]
         admin::init_schema(&self.conn).map_err(DatabaseError::Sqlite)?;
         Ok(())
     }
@@ -42,34 +53,80 @@ impl Database {
 
     // Delegate domain rule methods to rules module
     pub fn insert_rule(&self, rule: &DomainRule) -> Result<i64> {
-        rules::insert_rule(&self.conn, rule)
+        Ok(rules::insert_rule(&self.conn, rule)?)
     }
 
     pub fn upsert_rule(&self, rule: &DomainRule) -> Result<i64> {
-        rules::upsert_rule(&self.conn, rule)
+        Ok(rules::upsert_rule(&self.conn, rule)?)
+    }
+
+    pub fn get_approved_rules(&self) -> Result<crate::registry::Registry> {
+        Ok(rules::get_approved_rules(&self.conn)?)
     }
 
-    pub fn get_approved_rules(&self) -> Result<std::collections::HashMap<String, crate::cleaners::DomainCleaner>> {
-        rules::get_approved_rules(&self.conn)
+    pub fn get_approved_config(&self) -> Result<crate::config::ConfigFile> {
+        Ok(rules::get_approved_config(&self.conn)?)
     }
 
     pub fn get_all_rules(&self) -> Result<Vec<DomainRule>> {
-        rules::get_all_rules(&self.conn)
+        Ok(rules::get_all_rules(&self.conn)?)
     }
 
     pub fn get_approved_rules_for_api(&self) -> Result<Vec<DomainRule>> {
-        rules::get_approved_rules_for_api(&self.conn)
+        Ok(rules::get_approved_rules_for_api(&self.conn)?)
     }
 
     pub fn get_pending_rules_for_api(&self) -> Result<Vec<DomainRule>> {
-        rules::get_pending_rules_for_api(&self.conn)
+        Ok(rules::get_pending_rules_for_api(&self.conn)?)
     }
 
     pub fn update_rule_status(&self, id: i64, status: &str) -> Result<()> {
-        rules::update_rule_status(&self.conn, id, status)
+        Ok(rules::update_rule_status(&self.conn, id, status)?)
+    }
+
+    pub fn import_rules(&self, import: &crate::import::ImportReport, status: &str) -> Result<usize> {
+        Ok(rules::import_rules(&self.conn, import, status)?)
     }
 
     pub fn migrate_from_yaml(&self) -> Result<()> {
-        rules::migrate_from_yaml(&self.conn)
+        rules::migrate_from_yaml(&self.conn)?;
+        redirects::migrate_from_yaml(&self.conn)?;
+        amp::migrate_from_yaml(&self.conn)?;
+        Ok(rewrites::migrate_from_yaml(&self.conn)?)
+    }
+
+    // Delegate redirect rule methods to redirects module
+    pub fn insert_redirect_rule(&self, rule: &crate::redirects::RedirectRule, contributors: &[String], status: &str) -> Result<i64> {
+        Ok(redirects::insert_redirect_rule(&self.conn, rule, contributors, status)?)
+    }
+
+    pub fn get_approved_redirect_rules(&self) -> Result<Vec<crate::redirects::RedirectRule>> {
+        Ok(redirects::get_approved_redirect_rules(&self.conn)?)
+    }
+
+    // Delegate AMP rule methods to amp module
+    pub fn insert_amp_rule(&self, rule: &crate::amp::AmpRule, contributors: &[String], status: &str) -> Result<i64> {
+        Ok(amp::insert_amp_rule(&self.conn, rule, contributors, status)?)
     }
-}
\ No newline at end of file
+
+    pub fn get_approved_amp_rules(&self) -> Result<Vec<crate::amp::AmpRule>> {
+        Ok(amp::get_approved_amp_rules(&self.conn)?)
+    }
+
+    // Delegate host rewrite methods to rewrites module
+    pub fn insert_rewrite_rule(&self, rule: &crate::rewrites::RewriteRule, contributors: &[String], status: &str) -> Result<i64> {
+        Ok(rewrites::insert_rewrite_rule(&self.conn, rule, contributors, status)?)
+    }
+
+    pub fn get_approved_rewrite_rules(&self) -> Result<Vec<crate::rewrites::RewriteRule>> {
+        Ok(rewrites::get_approved_rewrite_rules(&self.conn)?)
+    }
+
+    pub fn set_rewrite_enabled(&self, name: &str, enabled: bool) -> Result<usize> {
+        Ok(rewrites::set_rewrite_enabled(&self.conn, name, enabled)?)
+    }
+
+    pub fn set_rewrite_target(&self, name: &str, target: &str) -> Result<usize> {
+        Ok(rewrites::set_rewrite_target(&self.conn, name, target)?)
+    }
+}
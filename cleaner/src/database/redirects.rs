@@ -0,0 +1,115 @@
+use rusqlite::{Connection, Result as SqliteResult, params};
+use crate::config::ConfigFile;
+use crate::redirects::RedirectRule;
+use super::rules::Result;
+
+/// Initialize the redirect rules schema
+pub fn init_schema(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS redirect_rules (
+            id INTEGER PRIMARY KEY,
+            domain TEXT NOT NULL,
+            path TEXT NOT NULL DEFAULT '',
+            param TEXT,
+            segment INTEGER,
+            contributor TEXT,
+            status TEXT DEFAULT 'pending'
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_redirect_rules_status ON redirect_rules(status)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Insert a new redirect rule into the database
+pub fn insert_redirect_rule(conn: &Connection, rule: &RedirectRule, contributors: &[String], status: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO redirect_rules (domain, path, param, segment, contributor, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            rule.domain,
+            rule.path,
+            rule.param,
+            rule.segment.map(|segment| segment as i64),
+            serde_json::to_string(contributors)?,
+            status,
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Whether the same redirect rule is already stored, whatever its status
+pub fn redirect_rule_exists(conn: &Connection, rule: &RedirectRule) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM redirect_rules WHERE domain = ? AND path = ? AND param IS ? AND segment IS ?",
+        params![rule.domain, rule.path, rule.param, rule.segment.map(|segment| segment as i64)],
+        |row| row.get(0),
+    )?;
+
+    Ok(count > 0)
+}
+
+/// Get approved redirect rules
+pub fn get_approved_redirect_rules(conn: &Connection) -> Result<Vec<RedirectRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT domain, path, param, segment FROM redirect_rules WHERE status = 'approved' ORDER BY id"
+    )?;
+
+    let rules = stmt.query_map([], |row| {
+        Ok(RedirectRule {
+            domain: row.get(0)?,
+            path: row.get(1)?,
+            param: row.get(2)?,
+            segment: row.get::<_, Option<i64>>(3)?.map(|segment| segment as usize),
+        })
+    })?.collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(rules)
+}
+
+/// Seed the redirect rules from YAML if the table is empty
+pub fn migrate_from_yaml(conn: &Connection) -> Result<()> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM redirect_rules",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if count > 0 {
+        tracing::info!("Database already has {} redirect rules, skipping migration", count);
+        return Ok(());
+    }
+
+    let config: ConfigFile = serde_yaml::from_str(crate::config::DOMAIN_RULES_YAML)?;
+    let contributors = vec!["system".to_string()];
+
+    for rule in &config.redirects {
+        insert_redirect_rule(conn, rule, &contributors, "approved")?;
+    }
+
+    tracing::info!("Successfully migrated {} redirect rules from YAML to database", config.redirects.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_redirect_rules_once() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        init_schema(&conn).expect("Should initialize schema");
+
+        migrate_from_yaml(&conn).expect("Should migrate redirect rules");
+        migrate_from_yaml(&conn).expect("Should skip the second migration");
+
+        let rules = get_approved_redirect_rules(&conn).expect("Should get redirect rules");
+        let config: ConfigFile = serde_yaml::from_str(crate::config::DOMAIN_RULES_YAML).unwrap();
+        assert_eq!(rules, config.redirects);
+    }
+}
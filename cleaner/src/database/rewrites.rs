@@ -0,0 +1,131 @@
+use rusqlite::{Connection, Result as SqliteResult, params};
+use crate::config::ConfigFile;
+use crate::rewrites::RewriteRule;
+use super::rules::Result;
+
+/// Initialize the host rewrite rules schema
+pub fn init_schema(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rewrite_rules (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            domain TEXT NOT NULL,
+            target TEXT NOT NULL,
+            path TEXT,
+            enabled INTEGER NOT NULL DEFAULT 0,
+            contributor TEXT,
+            status TEXT DEFAULT 'pending'
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_rewrite_rules_status ON rewrite_rules(status)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Insert a new host rewrite rule into the database
+pub fn insert_rewrite_rule(conn: &Connection, rule: &RewriteRule, contributors: &[String], status: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO rewrite_rules (name, domain, target, path, enabled, contributor, status)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            rule.name,
+            rule.domain,
+            rule.target,
+            rule.path,
+            rule.enabled,
+            serde_json::to_string(contributors)?,
+            status,
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Get approved host rewrite rules, enabled or not
+pub fn get_approved_rewrite_rules(conn: &Connection) -> Result<Vec<RewriteRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, domain, target, path, enabled FROM rewrite_rules WHERE status = 'approved' ORDER BY id"
+    )?;
+
+    let rules = stmt.query_map([], |row| {
+        Ok(RewriteRule {
+            name: row.get(0)?,
+            domain: row.get(1)?,
+            target: row.get(2)?,
+            path: row.get(3)?,
+            enabled: row.get(4)?,
+        })
+    })?.collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(rules)
+}
+
+/// Enable or disable every rewrite rule with this name, returning the number of rules changed
+pub fn set_rewrite_enabled(conn: &Connection, name: &str, enabled: bool) -> Result<usize> {
+    Ok(conn.execute(
+        "UPDATE rewrite_rules SET enabled = ? WHERE name = ?",
+        params![enabled, name],
+    )?)
+}
+
+/// Point every rewrite rule with this name to another front-end instance (e.g. a Nitter host)
+pub fn set_rewrite_target(conn: &Connection, name: &str, target: &str) -> Result<usize> {
+    Ok(conn.execute(
+        "UPDATE rewrite_rules SET target = ? WHERE name = ?",
+        params![target, name],
+    )?)
+}
+
+/// Seed the host rewrite rules from YAML if the table is empty
+pub fn migrate_from_yaml(conn: &Connection) -> Result<()> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM rewrite_rules",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if count > 0 {
+        tracing::info!("Database already has {} rewrite rules, skipping migration", count);
+        return Ok(());
+    }
+
+    let config: ConfigFile = serde_yaml::from_str(crate::config::DOMAIN_RULES_YAML)?;
+    let contributors = vec!["system".to_string()];
+
+    for rule in &config.rewrites {
+        insert_rewrite_rule(conn, rule, &contributors, "approved")?;
+    }
+
+    tracing::info!("Successfully migrated {} rewrite rules from YAML to database", config.rewrites.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_and_configures_rewrite_rules() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        init_schema(&conn).expect("Should initialize schema");
+
+        migrate_from_yaml(&conn).expect("Should migrate rewrite rules");
+        migrate_from_yaml(&conn).expect("Should skip the second migration");
+
+        let rules = get_approved_rewrite_rules(&conn).expect("Should get rewrite rules");
+        let config: ConfigFile = serde_yaml::from_str(crate::config::DOMAIN_RULES_YAML).unwrap();
+        assert_eq!(rules, config.rewrites);
+
+        assert_eq!(set_rewrite_target(&conn, "nitter", "nitter.example.org").unwrap(), 2);
+        assert_eq!(set_rewrite_enabled(&conn, "nitter", true).unwrap(), 2);
+
+        let rules = get_approved_rewrite_rules(&conn).expect("Should get rewrite rules");
+        let nitter: Vec<_> = rules.iter().filter(|rule| rule.name == "nitter").collect();
+        assert!(nitter.iter().all(|rule| rule.enabled && rule.target == "nitter.example.org"));
+    }
+}
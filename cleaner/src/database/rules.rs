@@ -1,20 +1,11 @@
-use rusqlite::{Connection, Result as SqliteResult, params};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use crate::cleaners::DomainCleaner;
+use rusqlite::{Connection, Params, Row, Result as SqliteResult, params};
+use crate::config::ConfigFile;
+use crate::import::ImportReport;
+use crate::registry::{Registry, DEFAULT_DOMAIN};
 use thiserror::Error;
 use tracing;
 
-/// DomainRule struct for the database
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DomainRule {
-    pub id: Option<i32>,
-    pub domain: String,
-    pub keys: Vec<String>,
-    pub starts_with: Vec<String>,
-    pub contributors: Vec<String>,
-    pub status: String, // "pending", "approved", "rejected"
-}
+pub use crate::config::{DomainRule, LAYER_DOMAIN, LAYER_GROUP};
 
 /// DatabaseError enum for the database
 #[derive(Debug, Error)]
@@ -23,11 +14,34 @@ pub enum DatabaseError {
     Sqlite(#[from] rusqlite::Error),
     #[error("JSON serialization error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
 }
 
 /// Result type for the database
 pub type Result<T> = std::result::Result<T, DatabaseError>;
 
+/// Columns added after the first release, created on existing databases by `init_schema`
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("layer", "TEXT NOT NULL DEFAULT 'domain'"),
+    ("exceptions", "TEXT NOT NULL DEFAULT '[]'"),
+    ("groups", "TEXT NOT NULL DEFAULT '[]'"),
+    ("ends_with", "TEXT NOT NULL DEFAULT '[]'"),
+    ("patterns", "TEXT NOT NULL DEFAULT '[]'"),
+    ("regexes", "TEXT NOT NULL DEFAULT '[]'"),
+    ("ignore_case", "INTEGER NOT NULL DEFAULT 0"),
+    ("path_segments", "TEXT NOT NULL DEFAULT '[]'"),
+    ("path_suffixes", "TEXT NOT NULL DEFAULT '[]'"),
+    ("path_params", "TEXT NOT NULL DEFAULT '[]'"),
+    ("mode", "TEXT NOT NULL DEFAULT 'remove'"),
+    ("keep", "TEXT NOT NULL DEFAULT '[]'"),
+    ("value_rules", "TEXT NOT NULL DEFAULT '[]'"),
+];
+
+/// Columns read by the rule queries, in the order expected by `rule_from_row`
+const RULE_COLUMNS: &str = "id, domain, layer, keys, starts_with, exceptions, groups, contributor, status, \
+    ends_with, patterns, regexes, ignore_case, path_segments, path_suffixes, path_params, mode, keep, value_rules";
+
 /// Initialize the domain rules schema
 pub fn init_schema(conn: &Connection) -> Result<()> {
     conn.execute(
@@ -42,6 +56,10 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    for (column, definition) in ADDED_COLUMNS {
+        add_column_if_missing(conn, column, definition)?;
+    }
+
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_domain_rules_status ON domain_rules(status)",
         [],
@@ -55,60 +73,146 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// Add a column to the domain rules table if an older schema doesn't have it yet
+fn add_column_if_missing(conn: &Connection, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('domain_rules')")?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    if !columns.iter().any(|name| name == column) {
+        conn.execute(&format!("ALTER TABLE domain_rules ADD COLUMN {column} {definition}"), [])?;
+    }
+
+    Ok(())
+}
+
+/// Parse a JSON array column
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, idx: usize) -> SqliteResult<Vec<T>> {
+    let json: String = row.get(idx)?;
+    serde_json::from_str(&json).map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))
+}
+
+/// Build a DomainRule from a row selected with `RULE_COLUMNS`
+fn rule_from_row(row: &Row) -> SqliteResult<DomainRule> {
+    Ok(DomainRule {
+        id: Some(row.get::<_, i64>(0)? as i32),
+        domain: row.get(1)?,
+        layer: row.get(2)?,
+        keys: json_column(row, 3)?,
+        starts_with: json_column(row, 4)?,
+        exceptions: json_column(row, 5)?,
+        groups: json_column(row, 6)?,
+        contributors: json_column(row, 7)?,
+        status: row.get(8)?,
+        ends_with: json_column(row, 9)?,
+        patterns: json_column(row, 10)?,
+        regexes: json_column(row, 11)?,
+        ignore_case: row.get(12)?,
+        path_segments: json_column(row, 13)?,
+        path_suffixes: json_column(row, 14)?,
+        path_params: json_column(row, 15)?,
+        mode: row
+            .get::<_, String>(16)?
+            .parse()
+            .map_err(rusqlite::Error::InvalidParameterName)?,
+        keep: json_column(row, 17)?,
+        values: json_column(row, 18)?,
+    })
+}
+
+/// Select the rules matching an optional SQL clause
+fn query_rules<P: Params>(conn: &Connection, clause: &str, params: P) -> Result<Vec<DomainRule>> {
+    let mut stmt = conn.prepare(&format!("SELECT {RULE_COLUMNS} FROM domain_rules {clause}"))?;
+    let rules = stmt.query_map(params, rule_from_row)?.collect::<SqliteResult<Vec<_>>>()?;
+    Ok(rules)
+}
+
+/// Append values to a list, keeping it sorted and without duplicates
+fn merge_unique<T: Ord + Clone>(target: &mut Vec<T>, values: &[T]) {
+    target.extend(values.iter().cloned());
+    target.sort();
+    target.dedup();
+}
+
 /// Insert a new rule into the database
 pub fn insert_rule(conn: &Connection, rule: &DomainRule) -> Result<i64> {
-    let keys_json = serde_json::to_string(&rule.keys)?;
-    let starts_with_json = serde_json::to_string(&rule.starts_with)?;
-    let contributors_json = serde_json::to_string(&rule.contributors)?;
-
     conn.execute(
-        "INSERT INTO domain_rules (domain, keys, starts_with, contributor, status) VALUES (?1, ?2, ?3, ?4, ?5)",
+        "INSERT INTO domain_rules (domain, layer, keys, starts_with, exceptions, groups, contributor, status,
+                                   ends_with, patterns, regexes, ignore_case, path_segments, path_suffixes, path_params, mode, keep, value_rules)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
         params![
             rule.domain,
-            keys_json,
-            starts_with_json,
-            contributors_json,
+            rule.layer,
+            serde_json::to_string(&rule.keys)?,
+            serde_json::to_string(&rule.starts_with)?,
+            serde_json::to_string(&rule.exceptions)?,
+            serde_json::to_string(&rule.groups)?,
+            serde_json::to_string(&rule.contributors)?,
             rule.status,
+            serde_json::to_string(&rule.ends_with)?,
+            serde_json::to_string(&rule.patterns)?,
+            serde_json::to_string(&rule.regexes)?,
+            rule.ignore_case,
+            serde_json::to_string(&rule.path_segments)?,
+            serde_json::to_string(&rule.path_suffixes)?,
+            serde_json::to_string(&rule.path_params)?,
+            rule.mode.as_str(),
+            serde_json::to_string(&rule.keep)?,
+            serde_json::to_string(&rule.values)?,
         ],
     )?;
 
     Ok(conn.last_insert_rowid())
 }
 
-/// Upsert a rule (insert or update existing)
+/// Upsert a rule (insert or update existing).
+/// Only rules of the same mode are merged, so an allowlist never gets mixed with removal rules.
 pub fn upsert_rule(conn: &Connection, rule: &DomainRule) -> Result<i64> {
-    let mut stmt = conn.prepare(
-        "SELECT id, keys, starts_with, contributor FROM domain_rules WHERE domain = ? AND status = ?",
+    let existing = query_rules(
+        conn,
+        "WHERE domain = ? AND layer = ? AND status = ? AND mode = ? LIMIT 1",
+        params![rule.domain, rule.layer, rule.status, rule.mode.as_str()],
     )?;
 
-    let mut rows = stmt.query(params![rule.domain, rule.status])?;
-
-    if let Some(row) = rows.next()? {
-        let id: i64 = row.get(0)?;
-        let mut keys: Vec<String> = serde_json::from_str(&row.get::<_, String>(1)?)?;
-        let mut starts_with: Vec<String> = serde_json::from_str(&row.get::<_, String>(2)?)?;
-        let mut contributors: Vec<String> = serde_json::from_str(&row.get::<_, String>(3)?)?;
+    if let Some(mut existing) = existing.into_iter().next() {
+        let id = existing.id.unwrap_or_default() as i64;
 
         // Merge and deduplicate
-        keys.extend(rule.keys.clone());
-        keys.sort();
-        keys.dedup();
-
-        starts_with.extend(rule.starts_with.clone());
-        starts_with.sort();
-        starts_with.dedup();
-
-        contributors.extend(rule.contributors.clone());
-        contributors.sort();
-        contributors.dedup();
+        merge_unique(&mut existing.keys, &rule.keys);
+        merge_unique(&mut existing.starts_with, &rule.starts_with);
+        merge_unique(&mut existing.ends_with, &rule.ends_with);
+        merge_unique(&mut existing.patterns, &rule.patterns);
+        merge_unique(&mut existing.regexes, &rule.regexes);
+        merge_unique(&mut existing.path_segments, &rule.path_segments);
+        merge_unique(&mut existing.path_suffixes, &rule.path_suffixes);
+        merge_unique(&mut existing.path_params, &rule.path_params);
+        merge_unique(&mut existing.exceptions, &rule.exceptions);
+        merge_unique(&mut existing.groups, &rule.groups);
+        merge_unique(&mut existing.keep, &rule.keep);
+        merge_unique(&mut existing.values, &rule.values);
+        merge_unique(&mut existing.contributors, &rule.contributors);
 
         // Update the existing row
         conn.execute(
-            "UPDATE domain_rules SET keys = ?, starts_with = ?, contributor = ? WHERE id = ?",
+            "UPDATE domain_rules SET keys = ?, starts_with = ?, exceptions = ?, groups = ?, contributor = ?,
+                                     ends_with = ?, patterns = ?, regexes = ?, ignore_case = ?,
+                                     path_segments = ?, path_suffixes = ?, path_params = ?, keep = ?, value_rules = ? WHERE id = ?",
             params![
-                serde_json::to_string(&keys)?,
-                serde_json::to_string(&starts_with)?,
-                serde_json::to_string(&contributors)?,
+                serde_json::to_string(&existing.keys)?,
+                serde_json::to_string(&existing.starts_with)?,
+                serde_json::to_string(&existing.exceptions)?,
+                serde_json::to_string(&existing.groups)?,
+                serde_json::to_string(&existing.contributors)?,
+                serde_json::to_string(&existing.ends_with)?,
+                serde_json::to_string(&existing.patterns)?,
+                serde_json::to_string(&existing.regexes)?,
+                existing.ignore_case || rule.ignore_case,
+                serde_json::to_string(&existing.path_segments)?,
+                serde_json::to_string(&existing.path_suffixes)?,
+                serde_json::to_string(&existing.path_params)?,
+                serde_json::to_string(&existing.keep)?,
+                serde_json::to_string(&existing.values)?,
                 id
             ],
         )?;
@@ -119,144 +223,78 @@ pub fn upsert_rule(conn: &Connection, rule: &DomainRule) -> Result<i64> {
     }
 }
 
-/// Get approved rules as a HashMap of DomainCleaner objects
-pub fn get_approved_rules(conn: &Connection) -> Result<HashMap<String, DomainCleaner>> {
-    let mut stmt = conn.prepare(
-        "SELECT domain, keys, starts_with FROM domain_rules WHERE status = 'approved'"
-    )?;
+/// Store the rules of an imported rule list with the given status, merged into the rules of the same domain and status.
+/// Redirect rules already stored are skipped. Returns the number of domain and redirect rules stored.
+pub fn import_rules(conn: &Connection, import: &ImportReport, status: &str) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let contributors = vec![import.source.clone()];
+    let mut stored = 0;
+
+    for rule in &import.rules {
+        upsert_rule(&tx, &DomainRule { status: status.to_string(), ..rule.clone() })?;
+        stored += 1;
+    }
 
-    let rules = stmt.query_map([], |row| {
-        let domain: String = row.get(0)?;
-        let keys_json: String = row.get(1)?;
-        let starts_with_json: String = row.get(2)?;
+    for redirect in &import.redirects {
+        if !super::redirects::redirect_rule_exists(&tx, redirect)? {
+            super::redirects::insert_redirect_rule(&tx, redirect, &contributors, status)?;
+            stored += 1;
+        }
+    }
 
-        let keys: Vec<String> = serde_json::from_str(&keys_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+    tx.commit()?;
+    Ok(stored)
+}
 
-        let starts_with: Vec<String> = serde_json::from_str(&starts_with_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+/// Group rules into the layers of a configuration: the `default` rule is the global layer
+pub fn rules_to_config(rules: &[DomainRule]) -> ConfigFile {
+    let mut config = ConfigFile::default();
 
-        // Create a DomainCleaner object for the domain and return it
-        let cleaner = DomainCleaner::new(
-            &keys.iter().map(String::as_str).collect::<Vec<_>>(),
-            &starts_with.iter().map(String::as_str).collect::<Vec<_>>(),
-        );
+    for rule in rules {
+        let layer = rule.to_config();
 
-        Ok((domain, cleaner))
-    })?.collect::<SqliteResult<Vec<_>>>()?;
+        if rule.domain == DEFAULT_DOMAIN {
+            config.defaults.extend(&layer);
+        } else if rule.layer == LAYER_GROUP {
+            config.groups.entry(rule.domain.clone()).or_default().extend(&layer);
+        } else {
+            config.domains.entry(rule.domain.clone()).or_default().extend(&layer);
+        }
+    }
 
-    Ok(rules.into_iter().collect())
+    config
 }
 
-/// Get all rules for the web API
-pub fn get_all_rules(conn: &Connection) -> Result<Vec<DomainRule>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, domain, keys, starts_with, contributor, status FROM domain_rules ORDER BY id DESC"
-    )?;
+/// Get the approved rules as a configuration, with the approved redirect, AMP and rewrite rules
+pub fn get_approved_config(conn: &Connection) -> Result<ConfigFile> {
+    let rules = query_rules(conn, "WHERE status = 'approved'", [])?;
 
-    let rules = stmt.query_map([], |row| {
-        let id: i64 = row.get(0)?;
-        let domain: String = row.get(1)?;
-        let keys_json: String = row.get(2)?;
-        let starts_with_json: String = row.get(3)?;
-        let contributors_json: String = row.get(4)?;
-        let status: String = row.get(5)?;
-
-        let keys: Vec<String> = serde_json::from_str(&keys_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        let starts_with: Vec<String> = serde_json::from_str(&starts_with_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        let contributors: Vec<String> = serde_json::from_str(&contributors_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        // Create a DomainRule object and return it
-        Ok(DomainRule {
-            id: Some(id as i32),
-            domain,
-            keys,
-            starts_with,
-            contributors,
-            status,
-        })
-    })?.collect::<SqliteResult<Vec<_>>>()?;
+    let mut config = rules_to_config(&rules);
+    config.redirects = super::redirects::get_approved_redirect_rules(conn)?;
+    config.amp = super::amp::get_approved_amp_rules(conn)?;
+    config.rewrites = super::rewrites::get_approved_rewrite_rules(conn)?;
 
-    Ok(rules)
+    Ok(config)
 }
 
-/// Get approved rules as DomainRule objects for the API
-pub fn get_approved_rules_for_api(conn: &Connection) -> Result<Vec<DomainRule>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, domain, keys, starts_with, contributor, status FROM domain_rules WHERE status = 'approved'"
-    )?;
+/// Get approved rules composed into a Registry of DomainCleaner objects
+pub fn get_approved_rules(conn: &Connection) -> Result<Registry> {
+    Ok(get_approved_config(conn)?.build_registry())
+}
 
-    let rules = stmt.query_map([], |row| {
-        let id: i64 = row.get(0)?;
-        let domain: String = row.get(1)?;
-        let keys_json: String = row.get(2)?;
-        let starts_with_json: String = row.get(3)?;
-        let contributor: String = row.get(4)?;
-        let status: String = row.get(5)?;
-
-        let keys: Vec<String> = serde_json::from_str(&keys_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        let starts_with: Vec<String> = serde_json::from_str(&starts_with_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        let contributors: Vec<String> = serde_json::from_str(&contributor)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        // Create a DomainRule object and return it
-        Ok(DomainRule {
-            id: Some(id as i32),
-            domain,
-            keys,
-            starts_with,
-            contributors,
-            status,
-        })
-    })?.collect::<SqliteResult<Vec<_>>>()?;
+/// Get all rules for the web API
+pub fn get_all_rules(conn: &Connection) -> Result<Vec<DomainRule>> {
+    query_rules(conn, "ORDER BY id DESC", [])
+}
 
-    Ok(rules)
+/// Get approved rules as DomainRule objects for the API
+pub fn get_approved_rules_for_api(conn: &Connection) -> Result<Vec<DomainRule>> {
+    query_rules(conn, "WHERE status = 'approved'", [])
 }
 
 /// Get pending rules for the API
 pub fn get_pending_rules_for_api(conn: &Connection) -> Result<Vec<DomainRule>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, domain, keys, starts_with, contributor, status FROM domain_rules WHERE status = 'pending'"
-    )?;
-    
-    let rules = stmt.query_map([], |row| {
-        let id: i64 = row.get(0)?;
-        let domain: String = row.get(1)?;
-        let keys_json: String = row.get(2)?;
-        let starts_with_json: String = row.get(3)?;
-        let contributor: String = row.get(4)?;
-        let status: String = row.get(5)?;
-
-        let keys: Vec<String> = serde_json::from_str(&keys_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        let starts_with: Vec<String> = serde_json::from_str(&starts_with_json)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        let contributors: Vec<String> = serde_json::from_str(&contributor)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-
-        // Create a DomainRule object and return it
-        Ok(DomainRule {
-            id: Some(id as i32),
-            domain,
-            keys,
-            starts_with,
-            contributors,
-            status,
-        })
-    })?.collect::<SqliteResult<Vec<_>>>()?;
-
-    Ok(rules)
+    query_rules(conn, "WHERE status = 'pending'", [])
 }
 
 /// Update rule status
@@ -283,79 +321,28 @@ pub fn migrate_from_yaml(conn: &Connection) -> Result<()> {
         return Ok(());
     }
 
-    let yaml_content = crate::config::DOMAIN_RULES_YAML;
-    
-    // Parse the YAML content into a serde_yaml::Value
-    let config: serde_yaml::Value = serde_yaml::from_str(yaml_content)
-        .map_err(|e| DatabaseError::Sqlite(rusqlite::Error::InvalidParameterName(e.to_string())))?;
-
-    let domains = config["domains"].as_mapping()
-        .ok_or_else(|| DatabaseError::Sqlite(rusqlite::Error::InvalidParameterName("Invalid YAML structure".to_string())))?;
-
-    let default_keys = config["defaults"]["keys"].as_sequence()
-        .unwrap_or(&serde_yaml::Sequence::new())
-        .iter()
-        .filter_map(|v| v.as_str())
-        .map(String::from)
-        .collect::<Vec<String>>();
-
-    let default_starts_with = config["defaults"]["starts_with"].as_sequence()
-        .unwrap_or(&serde_yaml::Sequence::new())
-        .iter()
-        .filter_map(|v| v.as_str())
-        .map(String::from)
-        .collect::<Vec<String>>();
-
-    let mut migrated_count = 0;
-
-    for (domain, rules) in domains {
-        let domain_str = domain.as_str().ok_or_else(|| DatabaseError::Sqlite(rusqlite::Error::InvalidParameterName("Invalid domain".to_string())))?;
-
-        // Include the "default" domain as it's needed for fallback
-        if domain_str == "default" {
-            // Use default settings for the default domain
-            let rule = DomainRule {
-                id: None,
-                domain: domain_str.to_string(),
-                keys: default_keys.clone(),
-                starts_with: default_starts_with.clone(),
-                contributors: vec!["system".to_string()],
-                status: "approved".to_string(),
-            };
-            insert_rule(conn, &rule)?;
-            migrated_count += 1;
-            continue;
-        }
+    let config: ConfigFile = serde_yaml::from_str(crate::config::DOMAIN_RULES_YAML)?;
 
-        let keys = rules["keys"].as_sequence()
-            .unwrap_or(&serde_yaml::Sequence::new())
-            .iter()
-            .filter_map(|v| v.as_str())
-            .map(String::from)
-            .collect::<Vec<String>>();
+    let mut rules = vec![DomainRule::from_config(DEFAULT_DOMAIN, LAYER_DOMAIN, &config.defaults)];
 
-        let starts_with = rules["starts_with"].as_sequence()
-            .unwrap_or(&serde_yaml::Sequence::new())
-            .iter()
-            .filter_map(|v| v.as_str())
-            .map(String::from)
-            .collect::<Vec<String>>();
+    let mut groups: Vec<_> = config.groups.iter().collect();
+    groups.sort_by_key(|(name, _)| *name);
+    for (name, group) in groups {
+        rules.push(DomainRule::from_config(name, LAYER_GROUP, group));
+    }
 
-        // Use domain-specific rules or fall back to defaults
-        let final_keys = if keys.is_empty() { &default_keys } else { &keys };
-        let final_starts_with = if starts_with.is_empty() { &default_starts_with } else { &starts_with };
+    let mut domains: Vec<_> = config.domains.iter().collect();
+    domains.sort_by_key(|(domain, _)| *domain);
+    for (domain, rule) in domains {
+        rules.push(DomainRule::from_config(domain, LAYER_DOMAIN, rule));
+    }
 
-        let rule = DomainRule {
-            id: None,
-            domain: domain_str.to_string(),
-            keys: final_keys.clone(),
-            starts_with: final_starts_with.clone(),
-            contributors: vec!["system".to_string()],
-            status: "approved".to_string(),
-        };
+    let migrated_count = rules.len();
 
+    for mut rule in rules {
+        rule.contributors = vec!["system".to_string()];
+        rule.status = "approved".to_string();
         insert_rule(conn, &rule)?;
-        migrated_count += 1;
     }
 
     tracing::info!("Successfully migrated {} rules from YAML to database", migrated_count);
@@ -365,6 +352,9 @@ pub fn migrate_from_yaml(conn: &Connection) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{RuleMode, ValueRule};
+    use crate::matchers::ValueMatch;
+    use crate::traits::UrlCleaner;
     use std::fs;
 
     #[test]
@@ -375,6 +365,9 @@ mod tests {
         // Create database connection
         let conn = Connection::open("test.db").expect("Should create database");
         init_schema(&conn).expect("Should initialize schema");
+        super::super::redirects::init_schema(&conn).expect("Should initialize redirect schema");
+        super::super::amp::init_schema(&conn).expect("Should initialize AMP schema");
+        super::super::rewrites::init_schema(&conn).expect("Should initialize rewrite schema");
         
         // Create a test rule
         let test_rule = DomainRule {
@@ -384,6 +377,7 @@ mod tests {
             starts_with: vec!["test_".to_string()],
             contributors: vec!["test@example.com".to_string()],
             status: "approved".to_string(),
+            ..Default::default()
         };
         
         // Insert rule
@@ -392,11 +386,156 @@ mod tests {
         
         // Get approved rules
         let rules = get_approved_rules(&conn).expect("Should get approved rules");
-        assert!(rules.contains_key("test.com"));
+        assert!(rules.contains_domain("test.com"));
         
         // Clean up
         let _ = fs::remove_file("test.db");
     }
-}
 
+    #[test]
+    fn database_and_yaml_compose_the_same_cleaners() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        init_schema(&conn).expect("Should initialize schema");
+        super::super::redirects::init_schema(&conn).expect("Should initialize redirect schema");
+        super::super::amp::init_schema(&conn).expect("Should initialize AMP schema");
+        super::super::rewrites::init_schema(&conn).expect("Should initialize rewrite schema");
+        migrate_from_yaml(&conn).expect("Should migrate rules");
+
+        let from_db = get_approved_rules(&conn).expect("Should get approved rules");
+        let from_yaml = crate::config::load_registry_from_file().expect("Should load registry");
+
+        for host in ["youtube.com", "m.youtube.com", "youtu.be", "instagram.com", "unknown.com"] {
+            for key in ["si", "feature", "yt_ref", "igsh", "utm_source", "v"] {
+                assert_eq!(
+                    crate::registry::get_cleaner_for_host_string(host, &from_db).unwrap().should_remove(key),
+                    crate::registry::get_cleaner_for_host_string(host, &from_yaml).unwrap().should_remove(key),
+                    "{host} {key}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn stores_path_suffix_rules() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        init_schema(&conn).expect("Should initialize schema");
+
+        let rule = DomainRule {
+            domain: "example.com".to_string(),
+            path_suffixes: vec!["_ref=*".to_string()],
+            ..Default::default()
+        };
+
+        upsert_rule(&conn, &rule).expect("Should insert rule");
+        upsert_rule(&conn, &DomainRule { path_suffixes: vec!["ref=sr_*".to_string()], ..rule }).expect("Should merge rule");
+
+        let pending = get_pending_rules_for_api(&conn).expect("Should read rules");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].path_suffixes, ["_ref=*", "ref=sr_*"]);
+    }
+
+    #[test]
+    fn adds_missing_columns_to_existing_databases() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        conn.execute(
+            "CREATE TABLE domain_rules (
+                id INTEGER PRIMARY KEY,
+                domain TEXT NOT NULL,
+                keys TEXT NOT NULL,
+                starts_with TEXT NOT NULL,
+                contributor TEXT,
+                status TEXT DEFAULT 'pending'
+            )",
+            [],
+        ).expect("Should create legacy table");
+        conn.execute(
+            "INSERT INTO domain_rules (domain, keys, starts_with, contributor, status)
+             VALUES ('default', '[]', '[\"utm_\"]', '[\"system\"]', 'approved')",
+            [],
+        ).expect("Should insert legacy rule");
+
+        init_schema(&conn).expect("Should migrate schema");
+
+        let rules = get_all_rules(&conn).expect("Should read rules");
+        assert_eq!(rules[0].layer, LAYER_DOMAIN);
+        assert_eq!(rules[0].mode, RuleMode::Remove);
+        assert!(rules[0].exceptions.is_empty());
+    }
+
+    #[test]
+    fn stores_keep_only_rules_separately() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        init_schema(&conn).expect("Should initialize schema");
+
+        let remove = DomainRule {
+            domain: "youtube.com".to_string(),
+            keys: vec!["si".to_string()],
+            ..Default::default()
+        };
+        let keep_only = DomainRule {
+            domain: "youtube.com".to_string(),
+            mode: RuleMode::KeepOnly,
+            keep: vec!["v".to_string(), "t".to_string()],
+            ..Default::default()
+        };
+
+        let remove_id = upsert_rule(&conn, &remove).expect("Should insert rule");
+        let keep_only_id = upsert_rule(&conn, &keep_only).expect("Should insert rule");
+        assert_ne!(remove_id, keep_only_id);
+
+        let pending = get_pending_rules_for_api(&conn).expect("Should read rules");
+        let rule = pending.iter().find(|rule| rule.mode == RuleMode::KeepOnly).expect("Should find keep_only rule");
+        assert_eq!(rule.keep, ["v", "t"]);
+
+        let json = serde_json::to_value(rule).expect("Should serialize rule");
+        assert_eq!(json["mode"], "keep_only");
+    }
+
+    #[test]
+    fn stores_value_rules() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        init_schema(&conn).expect("Should initialize schema");
+
+        let value = |kind, value: &str| ValueRule { key: "ref".to_string(), kind, value: value.to_string() };
+        let rule = DomainRule {
+            domain: "example.com".to_string(),
+            values: vec![value(ValueMatch::Exact, "twitter")],
+            ..Default::default()
+        };
+
+        upsert_rule(&conn, &rule).expect("Should insert rule");
+        upsert_rule(&conn, &DomainRule { values: vec![value(ValueMatch::Prefix, "share")], ..rule }).expect("Should merge rule");
 
+        let pending = get_pending_rules_for_api(&conn).expect("Should read rules");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].values, [value(ValueMatch::Exact, "twitter"), value(ValueMatch::Prefix, "share")]);
+    }
+
+    #[test]
+    fn imports_rules_with_the_chosen_status() {
+        let conn = Connection::open_in_memory().expect("Should create database");
+        init_schema(&conn).expect("Should initialize schema");
+        super::super::redirects::init_schema(&conn).expect("Should initialize redirect schema");
+
+        let import = ImportReport {
+            source: "list".to_string(),
+            rules: vec![DomainRule { domain: "example.com".to_string(), keys: vec!["ref".to_string()], ..Default::default() }],
+            redirects: vec![crate::redirects::RedirectRule {
+                domain: "out.example.com".to_string(),
+                path: String::new(),
+                param: Some("u".to_string()),
+                segment: None,
+            }],
+            unmapped: Vec::new(),
+        };
+
+        assert_eq!(import_rules(&conn, &import, "approved").expect("Should import rules"), 2);
+        // Importing the list again merges the domain rule and skips the redirect
+        assert_eq!(import_rules(&conn, &import, "approved").expect("Should import rules"), 1);
+
+        let approved = get_approved_rules_for_api(&conn).expect("Should read rules");
+        assert_eq!(approved.len(), 1);
+        assert_eq!(approved[0].keys, ["ref"]);
+        assert!(get_pending_rules_for_api(&conn).expect("Should read rules").is_empty());
+    }
+}
@@ -1,36 +1,578 @@
-use crate::registry::get_cleaner_for_host_string;
-use crate::config::load_registry_with_fallback;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use crate::amp::AmpRuleKind;
+use crate::canonical::{canonicalize, DEFAULT_STRIPPED_SUBDOMAINS};
+use crate::config::{load_registry_from_bundle, load_registry_from_yaml, DOMAIN_RULES_YAML};
+#[cfg(feature = "database")]
+use crate::config::{load_registry_from_database, load_registry_with_fallback};
+use crate::error::CleanError;
+use crate::matchers::ParamMatcher;
+use crate::parts::UrlParts;
+use crate::redirects::MAX_REDIRECT_DEPTH;
+use crate::report::{AmpStep, CleanReport, ParamLocation, RedirectStep, RemovedParam, RewriteStep, TextReport};
+use crate::rewrites::Rewrites;
+use crate::text::{escape_ampersands, find_urls, unescape_ampersands};
+use crate::registry::{get_cleaner_for_host_string, public_suffix_labels, Registry};
 use crate::traits::UrlCleaner;
 
-use url::Url;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+
+/// Scheme given to links written without one (`example.com/page`, `//example.com/page`)
+const DEFAULT_SCHEME: &str = "https";
+
+/// Engine built from the embedded rules, used by [`clean_url`], `None` if they can't be loaded
+static DEFAULT_ENGINE: LazyLock<Option<CleanerEngine>> = LazyLock::new(|| CleanerEngine::embedded().ok());
+
+/// How much of the URL the engine may rewrite besides the parts its rules remove
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlProfile {
+    /// Keep the URL exactly as it was written: only the query, fragment or path parts
+    /// matched by a rule change, and a URL no rule applies to comes back unchanged
+    #[default]
+    Preserve,
+    /// Serialize the URL the way the `url` crate does (`https://example.com` becomes `https://example.com/`)
+    Normalized,
+    /// Normalize the URL, then remove the `strip_subdomains`, drop empty query parameters,
+    /// sort the remaining ones and normalize percent-encoding, so equivalent links compare equal
+    Canonical,
+}
+
+impl FromStr for UrlProfile {
+    type Err = String;
 
-/// Removes tracking parameters from the URL
-pub fn clean_url(input: &str) -> Result<String, url::ParseError> {
-    let mut url = Url::parse(input)?;
-    let host = url.host_str().unwrap_or("");
+    fn from_str(profile: &str) -> Result<Self, Self::Err> {
+        match profile {
+            "preserve" => Ok(UrlProfile::Preserve),
+            "normalized" => Ok(UrlProfile::Normalized),
+            "canonical" => Ok(UrlProfile::Canonical),
+            _ => Err(format!("Unknown URL profile: {profile}")),
+        }
+    }
+}
 
-    let db_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "../cleaner/rules.db".to_string());
-    let registry = load_registry_with_fallback(&db_path)
-        .expect("Failed to load domain rules config file");
+/// Options of a single cleaning call
+#[derive(Debug, Clone, Default)]
+pub struct CleanOptions {
+    /// Also drop scroll-to-text directives (`#:~:text=...`) from the fragment
+    pub strip_text_fragments: bool,
 
-    let cleaner = get_cleaner_for_host_string(host, &registry);
+    pub profile: UrlProfile,
 
-    let cleaned_pairs: Vec<(String, String)> = url
-        .query_pairs()
-        .filter(|(key, _)| !cleaner.should_remove(key))
-        .map(|(key, value)| (key.into_owned(), value.into_owned()))
-        .collect();
+    /// Leading subdomains removed from the host by the canonical profile (`www`, `m`)
+    pub strip_subdomains: Vec<String>,
 
-    url.set_query(None);
+    /// Host rewrites to privacy front-ends to apply, none by default
+    pub rewrites: Rewrites,
 
-    if !cleaned_pairs.is_empty() {
-        let mut query = url.query_pairs_mut();
-        for (key, value) in cleaned_pairs {
-            query.append_pair(&key, &value);
+    /// URL the relative links (`/page?utm_source=x`, `../page`) are resolved against.
+    /// They are cleaned with the rules of its domain and given back relative.
+    pub base: Option<String>,
+}
+
+impl CleanOptions {
+    /// Options of the canonical profile, removing the `www.` and `m.` subdomains
+    pub fn canonical() -> Self {
+        Self {
+            profile: UrlProfile::Canonical,
+            strip_subdomains: DEFAULT_STRIPPED_SUBDOMAINS.iter().map(|subdomain| subdomain.to_string()).collect(),
+            ..Default::default()
         }
     }
+}
 
-    Ok(url.to_string())
+/// Long-lived URL cleaner holding an already built registry of domain rules.
+/// Build it once and share it (e.g. behind an `Arc`) instead of reloading rules for every URL.
+pub struct CleanerEngine {
+    registry: Registry,
+}
+
+impl CleanerEngine {
+    /// Create an engine from rules that were already loaded in memory.
+    /// The rules need a `default` cleaner for the domains without rules of their own.
+    pub fn from_rules(registry: Registry) -> Result<Self, CleanError> {
+        if registry.default_cleaner().is_none() {
+            return Err(CleanError::MissingDefaultLayer);
+        }
+
+        Ok(Self { registry })
+    }
+
+    /// Create an engine from a YAML document in the `domain_rules.yaml` format
+    pub fn from_yaml(yaml: &str) -> Result<Self, CleanError> {
+        Self::from_rules(load_registry_from_yaml(yaml)?)
+    }
+
+    /// Create an engine from a JSON rule bundle, as exported by `GET /api/rules/export/json`
+    pub fn from_bundle(json: &str) -> Result<Self, CleanError> {
+        Self::from_rules(load_registry_from_bundle(json)?)
+    }
+
+    /// Create an engine from the rules embedded in the crate (`config/domain_rules.yaml`)
+    pub fn embedded() -> Result<Self, CleanError> {
+        Self::from_yaml(DOMAIN_RULES_YAML)
+    }
+
+    /// Create an engine from the approved rules of a SQLite database.
+    /// The database is seeded from the embedded YAML if it is empty.
+    #[cfg(feature = "database")]
+    pub fn from_database(db_path: &str) -> Result<Self, CleanError> {
+        Self::from_rules(load_registry_from_database(db_path)?)
+    }
+
+    /// Create an engine from a SQLite database, falling back to the embedded YAML if it can't be read
+    #[cfg(feature = "database")]
+    pub fn from_database_with_fallback(db_path: &str) -> Result<Self, CleanError> {
+        Self::from_rules(load_registry_with_fallback(db_path)?)
+    }
+
+    /// Removes tracking parameters from the URL, unwrapping tracker redirects first
+    pub fn clean(&self, input: &str) -> Result<String, CleanError> {
+        self.clean_with(input, &CleanOptions::default())
+    }
+
+    /// Removes tracking parameters from the URL with the given options
+    pub fn clean_with(&self, input: &str, options: &CleanOptions) -> Result<String, CleanError> {
+        Ok(self.report_with(input, options)?.cleaned)
+    }
+
+    /// Cleans the URL and reports what was removed, by which rule, and the redirects unwrapped
+    pub fn report(&self, input: &str) -> Result<CleanReport, CleanError> {
+        self.report_with(input, &CleanOptions::default())
+    }
+
+    /// Cleans the URL with the given options and reports what was done.
+    /// Only http and https URLs can be cleaned. Links written without their scheme
+    /// (`example.com/page`, `//example.com/page`), or relative to the `base` option,
+    /// are cleaned as https or base URLs and given back in the form they were written.
+    pub fn report_with(&self, input: &str, options: &CleanOptions) -> Result<CleanReport, CleanError> {
+        let prefix = missing_prefix(input, options.base.as_deref())?;
+        if prefix.is_empty() {
+            return self.report_absolute(input, options);
+        }
+
+        let mut report = self.report_absolute(&format!("{prefix}{input}"), options)?;
+        if let Some(cleaned) = report.cleaned.strip_prefix(&prefix) {
+            report.cleaned = cleaned.to_string();
+        } else if let Some(cleaned) = relative_link(&report.cleaned, input, options) {
+            report.cleaned = cleaned;
+        }
+        report.changed = report.cleaned != input;
+        report.original = input.to_string();
+
+        Ok(report)
+    }
+
+    /// Cleans an absolute URL and reports what was done
+    fn report_absolute(&self, input: &str, options: &CleanOptions) -> Result<CleanReport, CleanError> {
+        let mut url = Url::parse(input)?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(CleanError::UnsupportedScheme(url.scheme().to_string()));
+        }
+
+        let mut source = input.to_string();
+        let mut redirects = Vec::new();
+        let mut amp = Vec::new();
+        let mut removed = Vec::new();
+
+        // Clean the destination of a redirect wrapper or an AMP cache instead of the wrapper itself
+        for _ in 0..MAX_REDIRECT_DEPTH {
+            if let Some(step) = self.unwrap_redirect(&url, &source) {
+                url = Url::parse(&step.to)?;
+                source = step.to.clone();
+                redirects.push(step);
+            } else if let Some(step) = self.unwrap_amp_cache(&url, &source) {
+                url = Url::parse(&step.to)?;
+                source = step.to.clone();
+                amp.push(step);
+            } else {
+                break;
+            }
+        }
+
+        // Then turn an AMP or mobile variant into the canonical page, each rule applying once
+        let host = url.host_str().unwrap_or("").to_string();
+        for rule in self.registry.amp_rules_for(&host).filter(|rule| rule.kind != AmpRuleKind::Cache) {
+            if let Some(to) = rule.apply(&url, &source) {
+                url = Url::parse(&to)?;
+                amp.push(AmpStep { from: source, to: to.clone(), rule: rule.clone() });
+                source = to;
+            }
+        }
+
+        // Rewrite the host to a privacy front-end if the call asks for it
+        let mut rewrite = None;
+        if let Some(step) = self.rewrite_host(&url, &source, &options.rewrites) {
+            url = Url::parse(&step.to)?;
+            source = step.to.clone();
+            rewrite = Some(step);
+        }
+
+        let host = url.host_str().unwrap_or("");
+
+        let cleaner = get_cleaner_for_host_string(host, &self.registry)?;
+
+        // Rules only touch the parts they match, the rest of the URL is copied as written
+        let base = match options.profile {
+            UrlProfile::Preserve => source.as_str(),
+            UrlProfile::Normalized | UrlProfile::Canonical => url.as_str(),
+        };
+        let parts = UrlParts::split(base);
+
+        let mut cleaned = parts.prefix.to_string();
+        match clean_path(parts.path, cleaner, &mut removed) {
+            Some(path) => cleaned.push_str(&path),
+            None => cleaned.push_str(parts.path),
+        }
+        if let Some(query) = parts.query {
+            push_part(&mut cleaned, '?', query, clean_query(query, cleaner, &mut removed));
+        }
+        if let Some(fragment) = parts.fragment {
+            push_part(&mut cleaned, '#', fragment, clean_fragment(fragment, cleaner, options, &mut removed));
+        }
+
+        if options.profile == UrlProfile::Canonical {
+            let mut url = Url::parse(&cleaned)?;
+            canonicalize(&mut url, &options.strip_subdomains);
+            cleaned = url.to_string();
+        }
+
+        Ok(CleanReport {
+            changed: cleaned != input,
+            original: input.to_string(),
+            cleaned,
+            removed,
+            redirects,
+            amp,
+            rewrite,
+        })
+    }
+
+    /// Cleans every http(s) URL found in the text, leaving the rest of the text untouched
+    pub fn clean_text(&self, text: &str) -> String {
+        self.report_text(text).cleaned
+    }
+
+    /// Cleans every http(s) URL found in the text and reports what was done to each of them.
+    /// URLs that can't be parsed are left as they are.
+    pub fn report_text(&self, text: &str) -> TextReport {
+        self.report_text_with(text, &CleanOptions::default())
+    }
+
+    /// Cleans every http(s) URL found in the text with the given options
+    pub fn report_text_with(&self, text: &str, options: &CleanOptions) -> TextReport {
+        let mut cleaned = String::with_capacity(text.len());
+        let mut urls = Vec::new();
+        let mut last = 0;
+
+        for range in find_urls(text) {
+            let found = &text[range.clone()];
+            let unescaped = unescape_ampersands(found);
+
+            let Ok(report) = self.report_with(unescaped.as_deref().unwrap_or(found), options) else {
+                continue;
+            };
+
+            cleaned.push_str(&text[last..range.start]);
+            match unescaped {
+                Some(_) => cleaned.push_str(&escape_ampersands(&report.cleaned)),
+                None => cleaned.push_str(&report.cleaned),
+            }
+            last = range.end;
+
+            urls.push(report);
+        }
+        cleaned.push_str(&text[last..]);
+
+        // A text made of a single scheme-less or relative link (`example.com/page?utm_source=x`) is cleaned as one
+        let link = text.trim();
+        let single_link = (urls.is_empty() && !link.is_empty() && !link.contains(char::is_whitespace))
+            .then(|| self.report_with(link, options).ok())
+            .flatten();
+        if let Some(report) = single_link {
+            cleaned = text.replacen(link, &report.cleaned, 1);
+            urls.push(report);
+        }
+
+        TextReport {
+            changed: urls.iter().any(|report| report.changed),
+            original: text.to_string(),
+            cleaned,
+            urls,
+        }
+    }
+
+    /// Find the AMP cache rule matching the URL and extract the original URL
+    fn unwrap_amp_cache(&self, url: &Url, source: &str) -> Option<AmpStep> {
+        let host = url.host_str()?;
+
+        self.registry
+            .amp_rules_for(host)
+            .filter(|rule| rule.kind == AmpRuleKind::Cache)
+            .find_map(|rule| {
+                Some(AmpStep {
+                    from: source.to_string(),
+                    to: rule.apply(url, source)?,
+                    rule: rule.clone(),
+                })
+            })
+    }
+
+    /// Apply the first selected host rewrite matching the URL
+    fn rewrite_host(&self, url: &Url, source: &str, rewrites: &Rewrites) -> Option<RewriteStep> {
+        let host = url.host_str()?;
+
+        self.registry
+            .rewrites_for(host)
+            .filter(|rule| rewrites.includes(&rule.name))
+            .find_map(|rule| {
+                Some(RewriteStep {
+                    from: source.to_string(),
+                    to: rule.apply(url, source)?,
+                    rule: rule.clone(),
+                })
+            })
+    }
+
+    /// Find the redirect rule matching the URL and extract its destination.
+    /// `source` is the URL as it was written, recorded in the step.
+    fn unwrap_redirect(&self, url: &Url, source: &str) -> Option<RedirectStep> {
+        let host = url.host_str()?;
+
+        self.registry.redirects_for(host).find_map(|rule| {
+            let destination = rule.extract(url).filter(|destination| Url::parse(destination).is_ok())?;
+
+            Some(RedirectStep {
+                from: source.to_string(),
+                to: destination,
+                rule: rule.clone(),
+            })
+        })
+    }
+}
+
+/// Get what has to be written before the input to make it an absolute URL: nothing for an absolute URL,
+/// the scheme for a scheme-relative link (`//example.com/page`), `https://` for a link starting with
+/// its host (`example.com/page`), or the part of the base URL a relative link (`../page`) replaces
+fn missing_prefix(input: &str, base: Option<&str>) -> Result<String, CleanError> {
+    let base = base.map(Url::parse).transpose()?;
+
+    match Url::parse(input) {
+        // `example.com:8080/page` parses as a URL with an `example.com` scheme
+        Ok(url) if !matches!(url.scheme(), "http" | "https") && base.is_none() && is_host_first(input) => {
+            Ok(format!("{DEFAULT_SCHEME}://"))
+        }
+        Ok(_) => Ok(String::new()),
+        Err(url::ParseError::RelativeUrlWithoutBase) => match base {
+            _ if input.starts_with("//") => Ok(format!("{}:", base.as_ref().map_or(DEFAULT_SCHEME, Url::scheme))),
+            Some(base) => Ok(relative_prefix(&base, input)),
+            None if is_host_first(input) => Ok(format!("{DEFAULT_SCHEME}://")),
+            None => Err(url::ParseError::RelativeUrlWithoutBase.into()),
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Get the part of the base URL a relative link is written after
+fn relative_prefix(base: &Url, link: &str) -> String {
+    let parts = UrlParts::split(base.as_str());
+
+    match link.chars().next() {
+        Some('/') => parts.prefix.to_string(),
+        Some('?') => format!("{}{}", parts.prefix, parts.path),
+        Some('#') => UrlParts { fragment: None, ..parts }.to_string(),
+        _ => {
+            let directory = parts.path.rfind('/').map_or(0, |index| index + 1);
+            format!("{}{}", parts.prefix, &parts.path[..directory])
+        }
+    }
+}
+
+/// Write a link cleaned against the base as a relative link again, once the profile has normalized it:
+/// after the part of the normalized base the link was written after, or relative to the normalized base
+/// when the link had dot segments (`../page`)
+fn relative_link(cleaned: &str, link: &str, options: &CleanOptions) -> Option<String> {
+    if options.profile == UrlProfile::Preserve || link.starts_with("//") {
+        return None;
+    }
+
+    let mut base = Url::parse(options.base.as_deref()?).ok()?;
+    if options.profile == UrlProfile::Canonical {
+        canonicalize(&mut base, &options.strip_subdomains);
+    }
+
+    match cleaned.strip_prefix(&relative_prefix(&base, link)) {
+        Some(cleaned) => Some(cleaned.to_string()),
+        None => base.make_relative(&Url::parse(cleaned).ok()?),
+    }
+}
+
+/// Whether scheme-less input starts with a plausible host (`www.youtube.com/watch?v=...`):
+/// labels ending with a known public suffix, with an optional port. Many file names (`main.rs`, `readme.md`)
+/// end with a top-level domain too, so the host must be followed by a path, a query or a fragment, or start with `www.`.
+fn is_host_first(input: &str) -> bool {
+    let authority = input.split(['/', '?', '#']).next().unwrap_or_default().to_ascii_lowercase();
+    let (host, port) = authority.split_once(':').unwrap_or((&authority, ""));
+
+    let labels = host.split('.').count();
+    let valid_labels = host.split('.').all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+    let link_like = input.len() > authority.len() || host.starts_with("www.");
+
+    valid_labels && link_like && port.chars().all(|c| c.is_ascii_digit()) && public_suffix_labels(host).is_some_and(|suffix| suffix < labels)
+}
+
+/// Record a removed parameter
+fn removed_param(name: &str, value: &str, location: ParamLocation, rule: &ParamMatcher) -> RemovedParam {
+    RemovedParam {
+        name: name.to_string(),
+        value: value.to_string(),
+        location,
+        rule: rule.clone(),
+    }
+}
+
+/// Append a query or a fragment, dropping it with its delimiter if cleaning emptied it
+fn push_part(url: &mut String, delimiter: char, original: &str, cleaned: Option<String>) {
+    let part = match &cleaned {
+        Some(cleaned) if cleaned.is_empty() => return,
+        Some(cleaned) => cleaned,
+        None => original,
+    };
+
+    url.push(delimiter);
+    url.push_str(part);
+}
+
+/// Removes the path segments, segment tails and matrix parameters (`;name=value`) matched by the cleaner.
+/// A segment loses its longest matching tail, but never all of it, that is left to the segment rules.
+/// Returns the new path, or `None` if nothing was removed.
+fn clean_path(path: &str, cleaner: &impl UrlCleaner, removed: &mut Vec<RemovedParam>) -> Option<String> {
+    let segments = path.strip_prefix('/')?;
+
+    let mut changed = false;
+    let mut kept = Vec::new();
+
+    for segment in segments.split('/') {
+        let mut parts = segment.split(';');
+        let name = parts.next().unwrap_or_default();
+
+        if let Some(rule) = cleaner.matching_segment_rule(name) {
+            removed.push(removed_param(segment, "", ParamLocation::PathSegment, rule));
+            changed = true;
+            continue;
+        }
+
+        let mut segment = name.to_string();
+        if let Some((start, rule)) = segment_suffix(name, cleaner) {
+            removed.push(removed_param(&name[start..], "", ParamLocation::PathSuffix, rule));
+            segment.truncate(start);
+            changed = true;
+        }
+
+        for param in parts {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            if let Some(rule) = cleaner.matching_path_param_rule(key) {
+                removed.push(removed_param(key, value, ParamLocation::PathParam, rule));
+                changed = true;
+            } else {
+                segment.push(';');
+                segment.push_str(param);
+            }
+        }
+
+        kept.push(segment);
+    }
+
+    changed.then(|| format!("/{}", kept.join("/")))
+}
+
+/// Finds the longest tail of a path segment matched by a suffix rule, with the position where it starts
+fn segment_suffix<'a>(segment: &str, cleaner: &'a impl UrlCleaner) -> Option<(usize, &'a ParamMatcher)> {
+    segment
+        .char_indices()
+        .skip(1)
+        .find_map(|(start, _)| Some((start, cleaner.matching_segment_suffix_rule(&segment[start..])?)))
+}
+
+/// Removes the query parameters matched by the cleaner.
+/// Only the matched `key=value` spans are cut out of the raw query: the surviving parameters
+/// keep their exact encoding, order and separators, and valueless flags (`?amp`) stay valueless.
+/// Returns the new query, or `None` if nothing was removed.
+fn clean_query(query: &str, cleaner: &impl UrlCleaner, removed: &mut Vec<RemovedParam>) -> Option<String> {
+    let mut changed = false;
+    let mut kept = Vec::new();
+
+    for pair in query.split('&') {
+        let rule = form_urlencoded::parse(pair.as_bytes())
+            .next()
+            .and_then(|(key, value)| Some((cleaner.matching_rule_with_value(&key, &value)?, key, value)));
+
+        match rule {
+            Some((rule, key, value)) => {
+                removed.push(removed_param(&key, &value, ParamLocation::Query, rule));
+                changed = true;
+            }
+            None => kept.push(pair),
+        }
+    }
+
+    changed.then(|| kept.join("&"))
+}
+
+/// Removes the tracking parameters of `key=value` fragments (`#xtor=RSS-8&page=2`)
+/// and, if asked, the text directives. Other fragments such as anchors and
+/// single-page-app routes (`#/path`, `#!/path`) are left untouched.
+/// Returns the new fragment, or `None` if nothing was removed.
+fn clean_fragment(fragment: &str, cleaner: &impl UrlCleaner, options: &CleanOptions, removed: &mut Vec<RemovedParam>) -> Option<String> {
+    let (fragment, directive) = match fragment.find(":~:") {
+        Some(index) => fragment.split_at(index),
+        None => (fragment, ""),
+    };
+
+    let mut cleaned = if is_param_fragment(fragment) {
+        let mut kept = Vec::new();
+
+        for pair in fragment.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let key = percent_decode_str(key).decode_utf8_lossy();
+            let value = percent_decode_str(value).decode_utf8_lossy();
+
+            match cleaner.matching_rule_with_value(&key, &value) {
+                Some(rule) => {
+                    removed.push(removed_param(&key, &value, ParamLocation::Fragment, rule));
+                }
+                None => kept.push(pair),
+            }
+        }
+
+        kept.join("&")
+    } else {
+        fragment.to_string()
+    };
+
+    if !options.strip_text_fragments {
+        cleaned.push_str(directive);
+    }
+
+    (cleaned.len() != fragment.len() + directive.len()).then_some(cleaned)
+}
+
+/// Whether a fragment is a list of `key=value` pairs rather than an anchor or a route
+fn is_param_fragment(fragment: &str) -> bool {
+    fragment.contains('=') && !fragment.starts_with(['/', '!'])
+}
+
+/// Removes tracking parameters from the URL using the embedded rules.
+/// Applications that serve many URLs should build their own [`CleanerEngine`] instead.
+pub fn clean_url(input: &str) -> Result<String, CleanError> {
+    match DEFAULT_ENGINE.as_ref() {
+        Some(engine) => engine.clean(input),
+        // Load the embedded rules again to report why they can't be used
+        None => CleanerEngine::embedded()?.clean(input),
+    }
 }
 
 
@@ -40,12 +582,18 @@ pub fn clean_url(input: &str) -> Result<String, url::ParseError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::amp::AmpRuleKind;
+    use crate::matchers::{MatcherKind, RuleLayer, RuleOrigin};
+
+    fn engine() -> &'static CleanerEngine {
+        DEFAULT_ENGINE.as_ref().unwrap()
+    }
 
     #[test]
     fn cleans_url_with_query_params() {
         let input = "https://example.com?utm_source=test&param=keep";
         let result = clean_url(input).unwrap();
-        
+
         assert!(result.contains("param=keep"));
         assert!(!result.contains("utm_source"));
     }
@@ -54,7 +602,7 @@ mod tests {
     fn handles_domain_specific_cleaning() {
         let input = "https://instagram.com?igsh=123&utm_source=test&param=keep";
         let result = clean_url(input).unwrap();
-        
+
         assert!(result.contains("param=keep"));
         assert!(!result.contains("igsh"));
         assert!(!result.contains("utm_source"));
@@ -64,8 +612,421 @@ mod tests {
     fn preserves_url_structure() {
         let input = "https://example.com/path?param=keep";
         let result = clean_url(input).unwrap();
-        
+
         assert!(result.starts_with("https://example.com/"));
         assert!(result.contains("param=keep"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn engine_can_be_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CleanerEngine>();
+    }
+
+    #[test]
+    fn engine_uses_its_own_rules() {
+        let yaml = "defaults:\n  starts_with: [\"utm_\"]\ndomains:\n  example.com:\n    keys: [\"ref\"]\n  default:\n    keys: []\n";
+        let engine = CleanerEngine::from_yaml(yaml).unwrap();
+
+        let result = engine.clean("https://example.com/?ref=abc&param=keep").unwrap();
+        assert_eq!(result, "https://example.com/?param=keep");
+    }
+
+    #[test]
+    fn engine_loads_exported_bundles() {
+        let yaml = "defaults:\n  starts_with: [\"utm_\"]\ndomains:\n  example.com:\n    keys: [\"ref\"]\n";
+        let config = serde_yaml::from_str(yaml).unwrap();
+        let json = crate::export::export(&config, crate::export::ExportFormat::Json).unwrap();
+
+        let engine = CleanerEngine::from_bundle(&json).unwrap();
+        assert_eq!(engine.clean("https://example.com/?ref=abc&utm_source=x&param=keep").unwrap(), "https://example.com/?param=keep");
+
+        let newer = json.replacen("\"version\": 1", "\"version\": 2", 1);
+        assert!(matches!(CleanerEngine::from_bundle(&newer), Err(CleanError::BundleVersion(2))));
+        assert!(matches!(CleanerEngine::from_bundle(yaml), Err(CleanError::Bundle(_))));
+    }
+
+    #[test]
+    fn cleans_links_without_scheme_in_the_same_form() {
+        assert_eq!(clean_url("example.com/page?utm_source=x&id=1").unwrap(), "example.com/page?id=1");
+        assert_eq!(clean_url("www.youtube.com/watch?v=abc&si=xyz").unwrap(), "www.youtube.com/watch?v=abc");
+        assert_eq!(clean_url("example.com:8080/?utm_source=x").unwrap(), "example.com:8080/");
+        assert_eq!(clean_url("//example.com/page?utm_source=x").unwrap(), "//example.com/page");
+
+        assert!(clean_url("notes.txt").is_err());
+        assert_eq!(engine().clean_text(" example.com/?utm_source=x "), " example.com/ ");
+        assert_eq!(clean_url("www.example.com").unwrap(), "www.example.com");
+    }
+
+    #[test]
+    fn leaves_file_names_ending_with_a_top_level_domain_alone() {
+        for name in ["main.rs", "readme.md", "example.com", "main.rs:12"] {
+            assert!(clean_url(name).is_err());
+        }
+
+        let options = CleanOptions { profile: UrlProfile::Normalized, ..Default::default() };
+        let report = engine().report_text_with("main.rs", &options);
+        assert_eq!(report.cleaned, "main.rs");
+        assert!(report.urls.is_empty());
+    }
+
+    #[test]
+    fn cleans_relative_links_against_a_base() {
+        let options = CleanOptions { base: Some("https://www.instagram.com/p/abc".to_string()), ..Default::default() };
+        let clean = |link| engine().clean_with(link, &options).unwrap();
+
+        assert_eq!(clean("/reel/xyz?igsh=1&id=2"), "/reel/xyz?id=2");
+        assert_eq!(clean("../def?igsh=1"), "../def");
+        assert_eq!(clean("#top"), "#top");
+        assert_eq!(clean("https://example.com/?utm_source=x"), "https://example.com/");
+
+        let options = CleanOptions { base: Some("not a url".to_string()), ..Default::default() };
+        assert!(engine().clean_with("/page", &options).is_err());
+    }
+
+    #[test]
+    fn keeps_relative_links_relative_under_every_profile() {
+        let base = Some("https://www.example.com/docs/guide".to_string());
+
+        let options = CleanOptions { base: base.clone(), ..CleanOptions::canonical() };
+        let clean = |link| engine().clean_with(link, &options).unwrap();
+        assert_eq!(clean("/page?utm_source=x&b=2&a=1"), "/page?a=1&b=2");
+        assert_eq!(clean("intro?b=2&a=1"), "intro?a=1&b=2");
+        assert_eq!(clean("?utm_source=x&b=2&a=1"), "?a=1&b=2");
+        assert_eq!(clean("https://www.example.org/?utm_source=x"), "https://example.org/");
+
+        let options = CleanOptions { base, profile: UrlProfile::Normalized, ..Default::default() };
+        let clean = |link| engine().clean_with(link, &options).unwrap();
+        assert_eq!(clean("../page?utm_source=x"), "../page");
+        assert_eq!(clean("./intro?utm_source=x#top"), "intro#top");
+    }
+
+    #[test]
+    fn reports_why_a_link_or_rules_cant_be_used() {
+        assert!(matches!(clean_url("Not a url"), Err(CleanError::Parse(_))));
+        assert!(matches!(clean_url("mailto:me@example.com?utm_source=x"), Err(CleanError::UnsupportedScheme(scheme)) if scheme == "mailto"));
+
+        assert!(matches!(CleanerEngine::from_yaml("domains: ["), Err(CleanError::Yaml(_))));
+        assert!(matches!(CleanerEngine::from_rules(Registry::new()), Err(CleanError::MissingDefaultLayer)));
+    }
+
+    #[test]
+    fn unwraps_and_cleans_redirect_wrappers() {
+        let result = clean_url("https://www.google.com/url?q=https%3A%2F%2Fwww.instagram.com%2Fp%2Fabc%3Figsh%3Dxyz&sa=D&usg=123").unwrap();
+        assert_eq!(result, "https://www.instagram.com/p/abc");
+
+        let result = clean_url("https://l.facebook.com/l.php?u=https%3A%2F%2Fexample.com%2F%3Futm_source%3Dfb%26id%3D1&h=AT0").unwrap();
+        assert_eq!(result, "https://example.com/?id=1");
+
+        let result = clean_url("https://eur01.safelinks.protection.outlook.com/?url=https%3A%2F%2Fexample.com%2Fdoc&data=05").unwrap();
+        assert_eq!(result, "https://example.com/doc");
+    }
+
+    #[test]
+    fn unwraps_nested_redirects_up_to_the_depth_limit() {
+        let mut url = "https://example.com/?utm_source=x&id=1".to_string();
+        for _ in 0..MAX_REDIRECT_DEPTH + 1 {
+            url = format!("https://www.google.com/url?q={}", percent_encoding::utf8_percent_encode(&url, percent_encoding::NON_ALPHANUMERIC));
+        }
+
+        let result = clean_url(&url).unwrap();
+        assert!(result.starts_with("https://www.google.com/url?q="));
+
+        let result = clean_url(&format!("https://t.umblr.com/redirect?z={}", percent_encoding::utf8_percent_encode("https://www.google.com/url?q=https%3A%2F%2Fexample.com%2F%3Futm_source%3Dx", percent_encoding::NON_ALPHANUMERIC))).unwrap();
+        assert_eq!(result, "https://example.com/");
+    }
+
+    #[test]
+    fn removes_tracking_path_segments() {
+        let result = clean_url("https://www.amazon.com/Some-Product/dp/B000123/ref=sr_1_1?keywords=lamp&qid=1700000000").unwrap();
+        assert_eq!(result, "https://www.amazon.com/Some-Product/dp/B000123?keywords=lamp");
+
+        let result = clean_url("https://example.com/shop;jsessionid=ABC123/item;JSESSIONID=x;color=red?id=1").unwrap();
+        assert_eq!(result, "https://example.com/shop/item;color=red?id=1");
+    }
+
+    #[test]
+    fn cuts_tracking_suffixes_from_path_segments() {
+        let yaml = r#"
+defaults:
+  starts_with: ["utm_"]
+domains:
+  example.com:
+    path_suffixes: ["_ref=*", "ref=sr_*"]
+"#;
+        let engine = CleanerEngine::from_yaml(yaml).unwrap();
+
+        let report = engine.report("https://example.com/shop/title_ref=sr_1_1/item?id=1").unwrap();
+        assert_eq!(report.cleaned, "https://example.com/shop/title/item?id=1");
+        assert_eq!(report.removed[0].name, "_ref=sr_1_1");
+        assert_eq!(report.removed[0].location, ParamLocation::PathSuffix);
+
+        assert_eq!(engine.clean("https://example.com/dp/B000123ref=sr_1_1").unwrap(), "https://example.com/dp/B000123");
+
+        // A tail is never the whole segment, and unmatched segments are left alone
+        assert_eq!(engine.clean("https://example.com/_ref=x/page").unwrap(), "https://example.com/_ref=x/page");
+        assert_eq!(engine.clean("https://example.com/preferences").unwrap(), "https://example.com/preferences");
+    }
+
+    #[test]
+    fn cleans_parameter_fragments() {
+        let result = clean_url("https://example.com/article#xtor=RSS-8").unwrap();
+        assert_eq!(result, "https://example.com/article");
+
+        let result = clean_url("https://example.com/article?id=1#utm_source=feed&page=2").unwrap();
+        assert_eq!(result, "https://example.com/article?id=1#page=2");
+    }
+
+    #[test]
+    fn keeps_anchors_and_hash_routes() {
+        for input in [
+            "https://example.com/docs#section-2",
+            "https://example.com/#/inbox?utm_source=mail",
+            "https://example.com/#!/profile?xtor=1",
+        ] {
+            assert_eq!(clean_url(input).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn strips_text_fragments_on_request() {
+        let input = "https://example.com/article#intro:~:text=tracking%20pixel";
+        assert_eq!(clean_url(input).unwrap(), input);
+
+        let options = CleanOptions { strip_text_fragments: true, ..Default::default() };
+        let result = engine().clean_with(input, &options).unwrap();
+        assert_eq!(result, "https://example.com/article#intro");
+
+        let result = engine().clean_with("https://example.com/article#:~:text=pixel", &options).unwrap();
+        assert_eq!(result, "https://example.com/article");
+    }
+
+    #[test]
+    fn reports_removed_params_and_their_rules() {
+        let report = engine()
+            .report("https://www.youtube.com/watch?v=abc&si=xyz&utm_source=share#xtor=RSS-8")
+            .unwrap();
+
+        assert!(report.changed);
+        assert_eq!(report.cleaned, "https://www.youtube.com/watch?v=abc");
+
+        let removed: Vec<_> = report
+            .removed
+            .iter()
+            .map(|param| (param.name.as_str(), param.location, param.rule.origin.clone().unwrap()))
+            .collect();
+        assert_eq!(removed, [
+            ("si", ParamLocation::Query, RuleOrigin::new(RuleLayer::Group, "youtube")),
+            ("utm_source", ParamLocation::Query, RuleOrigin::new(RuleLayer::Global, "default")),
+            ("xtor", ParamLocation::Fragment, RuleOrigin::new(RuleLayer::Global, "default")),
+        ]);
+        assert_eq!(report.removed[1].value, "share");
+        assert_eq!(report.removed[1].rule.kind, MatcherKind::Prefix);
+    }
+
+    #[test]
+    fn reports_redirect_steps() {
+        let report = clean_report("https://www.google.com/url?q=https%3A%2F%2Fexample.com%2F%3Fid%3D1&sa=D");
+
+        assert_eq!(report.redirects.len(), 1);
+        assert_eq!(report.redirects[0].to, "https://example.com/?id=1");
+        assert_eq!(report.redirects[0].rule.param.as_deref(), Some("q"));
+        assert_eq!(report.cleaned, "https://example.com/?id=1");
+        assert!(report.removed.is_empty());
+    }
+
+    #[test]
+    fn reports_unchanged_urls() {
+        let report = clean_report("https://example.com/page?id=1");
+
+        assert!(!report.changed);
+        assert_eq!(report.cleaned, report.original);
+    }
+
+    fn clean_report(input: &str) -> CleanReport {
+        engine().report(input).unwrap()
+    }
+
+    #[test]
+    fn cleans_every_url_in_text() {
+        let text = "Check <https://example.com/a?utm_source=x&id=1>, [this](https://www.instagram.com/p/abc?igsh=xyz) \
+                    and https://example.com/b?ref=1&amp;utm_medium=mail!";
+
+        let report = engine().report_text(text);
+
+        assert_eq!(
+            report.cleaned,
+            "Check <https://example.com/a?id=1>, [this](https://www.instagram.com/p/abc) \
+             and https://example.com/b?ref=1!",
+        );
+        assert_eq!(report.urls.len(), 3);
+        assert!(report.changed);
+    }
+
+    #[test]
+    fn keeps_html_escaped_urls_escaped_once() {
+        let text = r#"<a href="https://example.com/?id=1&amp;q=&lt;b&gt;&amp;utm_source=x&amp;page=2">"#;
+        assert_eq!(engine().clean_text(text), r#"<a href="https://example.com/?id=1&amp;q=&lt;b&gt;&amp;page=2">"#);
+
+        let escaped = engine().clean_text("https://example.com/?id=1&amp;page=2&amp;amp;x");
+        assert_eq!(escaped, "https://example.com/?id=1&amp;page=2&amp;amp;x");
+
+        // Raw separators stay raw when some of them are escaped
+        let mixed = "https://example.com/?utm_source=x&id=1&amp;page=2";
+        assert_eq!(engine().clean_text(mixed), "https://example.com/?id=1&amp;page=2");
+    }
+
+    #[test]
+    fn keeps_text_without_tracking_urls() {
+        let text = "Nothing to see at https://example.com/page?id=1 (really) or ftp://example.com";
+        assert_eq!(engine().clean_text(text), text);
+    }
+
+    #[test]
+    fn keeps_the_raw_query_of_surviving_params() {
+        let result = clean_url("https://example.com/?q=a%20b+c&utm_source=x&flag&sig=AB%2Fcd%3D&&z=%7e").unwrap();
+        assert_eq!(result, "https://example.com/?q=a%20b+c&flag&sig=AB%2Fcd%3D&&z=%7e");
+
+        let result = clean_url("https://example.com/?utm_source=x&utm%5Fmedium=y").unwrap();
+        assert_eq!(result, "https://example.com/");
+    }
+
+    #[test]
+    fn preserves_the_shape_of_the_url() {
+        for input in [
+            "https://example.com",
+            "HTTPS://Example.COM:443/Path/?q=a b",
+            "https://bücher.example/straße?id=1#Top",
+            "https://example.com/?",
+        ] {
+            assert_eq!(clean_url(input).unwrap(), input);
+        }
+
+        let result = clean_url("HTTPS://Example.COM?utm_source=x&q=a b").unwrap();
+        assert_eq!(result, "HTTPS://Example.COM?q=a b");
+    }
+
+    #[test]
+    fn normalizes_on_request() {
+        let options = CleanOptions { profile: UrlProfile::Normalized, ..Default::default() };
+
+        let result = engine().clean_with("HTTPS://Example.COM?utm_source=x", &options).unwrap();
+        assert_eq!(result, "https://example.com/");
+    }
+
+    #[test]
+    fn canonicalizes_on_request() {
+        let result = engine()
+            .clean_with("HTTPS://WWW.Example.COM:443/%7euser?b=2&utm_source=x&a=&a=1", &CleanOptions::canonical())
+            .unwrap();
+        assert_eq!(result, "https://example.com/~user?a=1&b=2");
+    }
+
+    #[test]
+    fn unwraps_amp_caches() {
+        let result = clean_url("https://www.google.com/amp/s/www.bbc.co.uk/news/article.amp?utm_source=x").unwrap();
+        assert_eq!(result, "https://www.bbc.co.uk/news/article");
+
+        let result = clean_url("https://example-com.cdn.ampproject.org/c/s/example.com/news/story?id=1").unwrap();
+        assert_eq!(result, "https://example.com/news/story?id=1");
+
+        let result = clean_url("https://www.google.co.uk/amp/s/www.cnbc.com/amp/2024/01/02/story.html").unwrap();
+        assert_eq!(result, "https://www.cnbc.com/2024/01/02/story.html");
+
+        let report = clean_report("https://www.google.com/amp/example.com/page");
+        assert_eq!(report.cleaned, "http://example.com/page");
+        assert_eq!(report.amp[0].rule.kind, AmpRuleKind::Cache);
+    }
+
+    #[test]
+    fn removes_amp_markers_and_mobile_hosts() {
+        assert_eq!(clean_url("https://www.bbc.co.uk/news/world-123.amp?id=2").unwrap(), "https://www.bbc.co.uk/news/world-123?id=2");
+        assert_eq!(clean_url("https://amp.theguardian.com/world/2024/story").unwrap(), "https://theguardian.com/world/2024/story");
+        assert_eq!(clean_url("https://en.m.wikipedia.org/wiki/Rust").unwrap(), "https://en.wikipedia.org/wiki/Rust");
+        assert_eq!(clean_url("https://mobile.twitter.com/rustlang/status/1").unwrap(), "https://twitter.com/rustlang/status/1");
+        assert_eq!(clean_url("https://techcrunch.com/2024/01/02/story/?amp=1&id=2").unwrap(), "https://techcrunch.com/2024/01/02/story/?id=2");
+        assert_eq!(clean_url("https://www.independent.co.uk/news/story-b123.html?amp").unwrap(), "https://www.independent.co.uk/news/story-b123.html");
+        assert_eq!(clean_url("https://www.cnbc.com/amp/2024/01/02/story.html").unwrap(), "https://www.cnbc.com/2024/01/02/story.html");
+        assert_eq!(clean_url("https://www.nbcnews.com/news/amp/rcna123").unwrap(), "https://www.nbcnews.com/news/rcna123");
+
+        // Only whole labels and segments are AMP markers
+        for input in ["https://m.example.com/", "https://www.bbc.co.uk/ampere?amplitude=1", "https://www.bbc.co.uk/.amp"] {
+            assert_eq!(clean_url(input).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn keeps_amp_lookalikes_of_other_sites() {
+        for input in [
+            "https://shop.example.com/electronics/amp/guitar-amp-50w",
+            "https://music.example.com/gear?amp=marshall&id=2",
+            "https://files.example.com/presets/crunch.amp",
+        ] {
+            assert_eq!(clean_url(input).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn removes_mobile_labels_under_wildcard_domains() {
+        let yaml = r#"
+defaults:
+  starts_with: ["utm_"]
+domains: {}
+amp:
+  - kind: mobile_host
+    domain: "*.wikipedia.org"
+    value: m
+  - kind: mobile_host
+    domain: example.*
+    value: mobile
+"#;
+        let engine = CleanerEngine::from_yaml(yaml).unwrap();
+
+        assert_eq!(engine.clean("https://en.m.wikipedia.org/wiki/Rust").unwrap(), "https://en.wikipedia.org/wiki/Rust");
+        assert_eq!(engine.clean("https://mobile.example.co.uk/page").unwrap(), "https://example.co.uk/page");
+        assert_eq!(engine.clean("https://mobile.example.com/page").unwrap(), "https://example.com/page");
+
+        // The label must be a subdomain of the rule domain
+        assert_eq!(engine.clean("https://mobile.example-shop.com/").unwrap(), "https://mobile.example-shop.com/");
+        assert_eq!(engine.clean("https://en.m.wikipedia.com/").unwrap(), "https://en.m.wikipedia.com/");
+    }
+
+    #[test]
+    fn rewrites_hosts_only_when_selected() {
+        let input = "https://youtu.be/abc?si=xyz&t=30";
+        assert_eq!(clean_url(input).unwrap(), "https://youtu.be/abc?t=30");
+
+        let options = CleanOptions { rewrites: Rewrites::All, ..Default::default() };
+        let report = engine().report_with(input, &options).unwrap();
+        assert_eq!(report.cleaned, "https://youtube.com/watch?v=abc&t=30");
+        assert_eq!(report.rewrite.unwrap().rule.name, "youtube");
+
+        let options = CleanOptions { rewrites: Rewrites::Named(vec!["old_reddit".to_string()]), ..Default::default() };
+        assert_eq!(engine().clean_with(input, &options).unwrap(), "https://youtu.be/abc?t=30");
+        assert_eq!(engine().clean_with("https://www.reddit.com/r/rust", &options).unwrap(), "https://old.reddit.com/r/rust");
+
+        // Disabled rules never apply
+        let options = CleanOptions { rewrites: Rewrites::All, ..Default::default() };
+        assert_eq!(engine().clean_with("https://x.com/rustlang", &options).unwrap(), "https://x.com/rustlang");
+    }
+
+    #[test]
+    fn removes_params_only_for_matching_values() {
+        let yaml = r#"
+defaults:
+  keys: []
+domains:
+  example.com:
+    values:
+      - { key: ref, value: twitter }
+      - { key: source, kind: prefix, value: share }
+"#;
+        let engine = CleanerEngine::from_yaml(yaml).unwrap();
+
+        let result = engine.clean("https://example.com/?ref=twitter&source=share_sheet&x=1#ref=twitter").unwrap();
+        assert_eq!(result, "https://example.com/?x=1");
+
+        let input = "https://example.com/tree?ref=refs%2Fheads%2Fmain&source=rss";
+        assert_eq!(engine.clean(input).unwrap(), input);
+    }
+}
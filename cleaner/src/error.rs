@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+use crate::export::{ExportError, BUNDLE_VERSION};
+use crate::import::ImportError;
+
+#[cfg(feature = "database")]
+use crate::database::DatabaseError;
+
+/// Error of the cleaner: a link it can't clean, or rules it can't load
+#[derive(Debug, Error)]
+pub enum CleanError {
+    /// The input is not an absolute URL
+    #[error("invalid URL: {0}")]
+    Parse(#[from] url::ParseError),
+
+    /// The URL is valid but isn't a web link (`mailto:`, `ftp://`)
+    #[error("unsupported scheme `{0}`, only http and https links can be cleaned")]
+    UnsupportedScheme(String),
+
+    /// The rules have no `default` layer to clean the domains without rules of their own
+    #[error("the rules have no `default` layer")]
+    MissingDefaultLayer,
+
+    /// The rules are not valid YAML in the `domain_rules.yaml` format
+    #[error("invalid rules: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    /// The rule bundle is not valid JSON in the format of the JSON export
+    #[error("invalid rule bundle: {0}")]
+    Bundle(#[from] serde_json::Error),
+
+    /// The rule bundle was exported in another version of the format
+    #[error("rule bundle version {0} is not supported, expected version {BUNDLE_VERSION}")]
+    BundleVersion(u32),
+
+    /// A third-party rule list can't be read
+    #[error("invalid rule list: {0}")]
+    Import(#[from] ImportError),
+
+    /// The rules can't be written in an export format
+    #[error("export failed: {0}")]
+    Export(#[from] ExportError),
+
+    /// The rules database can't be opened or read
+    #[cfg(feature = "database")]
+    #[error("rules database error: {0}")]
+    Database(#[from] DatabaseError),
+}
+
+impl CleanError {
+    /// Whether the error comes from the link being cleaned rather than from the rules
+    pub fn is_invalid_input(&self) -> bool {
+        matches!(self, CleanError::Parse(_) | CleanError::UnsupportedScheme(_))
+    }
+}
@@ -0,0 +1,507 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::{ConfigFile, RuleConfig, RuleMode};
+use crate::error::CleanError;
+use crate::import::clearurls::{DataFile, Provider, ANY_TLD, GLOBAL_PROVIDER};
+use crate::import::Unmapped;
+use crate::matchers::{glob_to_regex, MatcherKind, MatcherSet, ParamMatcher, ValueMatch};
+use crate::redirects::RedirectRule;
+use crate::registry::DEFAULT_DOMAIN;
+
+/// Version of the JSON bundle format, increased on incompatible changes
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Character class of a query parameter name, so exported wildcards can't run into the value
+const NAME_CHAR: &str = "[^&=]";
+
+/// Character class of a path segment
+const SEGMENT_CHAR: &str = "[^/?#]";
+
+/// Error writing rules in an export format
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Format of exported rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// The `domain_rules.yaml` format, read back as a `ConfigFile`
+    #[serde(rename = "yaml")]
+    Yaml,
+    /// A versioned JSON bundle of every rule
+    #[serde(rename = "json")]
+    Json,
+    /// The `data.json` format of the ClearURLs extension
+    #[serde(rename = "clearurls")]
+    ClearUrls,
+    /// `$removeparam` filters of AdGuard and uBlock Origin
+    #[serde(rename = "adguard")]
+    AdGuard,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Yaml => "yaml",
+            ExportFormat::Json => "json",
+            ExportFormat::ClearUrls => "clearurls",
+            ExportFormat::AdGuard => "adguard",
+        }
+    }
+
+    /// MIME type of the exported document
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Yaml => "application/yaml",
+            ExportFormat::Json | ExportFormat::ClearUrls => "application/json",
+            ExportFormat::AdGuard => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "yaml" => Ok(ExportFormat::Yaml),
+            "json" => Ok(ExportFormat::Json),
+            "clearurls" => Ok(ExportFormat::ClearUrls),
+            "adguard" => Ok(ExportFormat::AdGuard),
+            _ => Err(format!("Unknown export format: {format}")),
+        }
+    }
+}
+
+/// The JSON bundle: every rule of a configuration, with the version of the format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleBundle {
+    pub version: u32,
+    #[serde(flatten)]
+    pub rules: ConfigFile,
+}
+
+/// Exported rules, along with the parameter rules the format couldn't express
+#[derive(Debug, Clone, Default)]
+pub struct ExportReport {
+    pub document: String,
+    pub unmapped: Vec<Unmapped>,
+}
+
+/// Write the rules of a configuration in an export format.
+/// The YAML and JSON formats keep every rule. ClearURLs and AdGuard have no groups, AMP rules or rewrites,
+/// so each domain gets the rules of its groups and the rules they can't express are left out.
+pub fn export(config: &ConfigFile, format: ExportFormat) -> Result<String, CleanError> {
+    Ok(export_report(config, format)?.document)
+}
+
+/// Write the rules of a configuration in an export format, reporting the rules
+/// a ClearURLs or AdGuard export leaves out or can't express as they are
+pub fn export_report(config: &ConfigFile, format: ExportFormat) -> Result<ExportReport, CleanError> {
+    Ok(write_report(config, format)?)
+}
+
+/// Write the export, failing with the error of the YAML or JSON serializer
+fn write_report(config: &ConfigFile, format: ExportFormat) -> Result<ExportReport, ExportError> {
+    match format {
+        ExportFormat::Yaml => Ok(ExportReport { document: serde_yaml::to_string(config)?, ..Default::default() }),
+        ExportFormat::Json => Ok(ExportReport {
+            document: serde_json::to_string_pretty(&RuleBundle {
+                version: BUNDLE_VERSION,
+                rules: config.clone(),
+            })?,
+            ..Default::default()
+        }),
+        ExportFormat::ClearUrls => {
+            let (data, unmapped) = clearurls_data(config);
+            Ok(ExportReport { document: serde_json::to_string_pretty(&data)?, unmapped })
+        }
+        ExportFormat::AdGuard => {
+            let (document, unmapped) = removeparam_filters(config);
+            Ok(ExportReport { document, unmapped })
+        }
+    }
+}
+
+/// Get the global layer and every domain layer merged with its groups, for the formats without groups
+fn flat_layers(config: &ConfigFile) -> Vec<(&str, RuleConfig)> {
+    let mut global = config.defaults.clone();
+    if let Some(default) = config.domains.get(DEFAULT_DOMAIN) {
+        global.extend(default);
+    }
+
+    let mut layers = vec![(DEFAULT_DOMAIN, global)];
+    for (domain, rule) in &config.domains {
+        if domain == DEFAULT_DOMAIN {
+            continue;
+        }
+
+        let mut layer = RuleConfig::default();
+        for group in rule.groups.iter().filter_map(|group| config.groups.get(group)) {
+            layer.extend(group);
+        }
+        layer.extend(rule);
+        layers.push((domain, layer));
+    }
+
+    layers
+}
+
+/// Escape a domain or path for a ClearURLs pattern, which are JavaScript regexes
+fn escape_pattern(value: &str) -> String {
+    regex::escape(value).replace('/', r"\/")
+}
+
+/// Pattern of the URLs of a domain and its subdomains, `*.` requiring a subdomain and `.*` allowing any top-level domain
+fn domain_pattern(domain: &str) -> String {
+    let (subdomains, domain) = match domain.strip_prefix("*.") {
+        Some(domain) => (r"(?:[a-z0-9-]+\.)+?", domain),
+        None => (r"(?:[a-z0-9-]+\.)*?", domain),
+    };
+
+    match domain.strip_suffix(".*") {
+        Some(name) => format!(r"^https?:\/\/{subdomains}{}{ANY_TLD}", escape_pattern(name)),
+        None => format!(r"^https?:\/\/{subdomains}{}", escape_pattern(domain)),
+    }
+}
+
+/// Pattern extracting the destination of a redirect rule
+fn redirection_pattern(rule: &RedirectRule) -> Option<String> {
+    let mut pattern = domain_pattern(&rule.domain);
+
+    match (&rule.param, rule.segment) {
+        (Some(param), _) => {
+            pattern.push_str(&escape_pattern(&rule.path));
+            pattern.push_str(&format!(".*?[?&]{}=([^&]*)", regex::escape(param)));
+        }
+        (None, Some(segment)) => {
+            if !rule.path.is_empty() {
+                pattern.push_str(&format!("(?={})", escape_pattern(&rule.path)));
+            }
+            pattern.push_str(&format!(r"\/(?:[^\/]*\/){{{segment}}}(.*)"));
+        }
+        (None, None) => return None,
+    }
+
+    Some(pattern)
+}
+
+/// Build the ClearURLs providers: `globalRules` for the global layer and one provider per domain.
+/// ClearURLs matches rules in any letter case, so `ignore_case` layers keep their meaning.
+/// Its `exceptions` skip whole URLs rather than parameters, so parameter exceptions, `keep_only` rules
+/// and value conditions are reported as unmapped.
+fn clearurls_data(config: &ConfigFile) -> (DataFile, Vec<Unmapped>) {
+    let mut data = DataFile::default();
+    let mut unmapped = Vec::new();
+
+    for (domain, layer) in flat_layers(config) {
+        let (name, url_pattern) = match domain {
+            DEFAULT_DOMAIN => (GLOBAL_PROVIDER.to_string(), ".*".to_string()),
+            domain => (domain.to_string(), domain_pattern(domain)),
+        };
+
+        let raw_rules = layer
+            .path_segment_matchers()
+            .iter()
+            .map(|matcher| format!(r"\/{}(?=[\/?#]|$)", glob_to_regex(&matcher.pattern, SEGMENT_CHAR)))
+            .chain(
+                layer
+                    .path_suffix_matchers()
+                    .iter()
+                    .map(|matcher| format!(r"(?<=[^\/]){}(?=[\/?#;]|$)", glob_to_regex(&matcher.pattern, SEGMENT_CHAR))),
+            )
+            .chain(layer.path_params.iter().map(|name| format!(";{}=[^\\/?#;]*", regex::escape(name))))
+            .collect();
+
+        for exception in &layer.exceptions {
+            unmapped.push(Unmapped::new(&name, "exceptions", exception, "parameter exceptions are not supported"));
+        }
+        if layer.mode == RuleMode::KeepOnly {
+            unmapped.push(Unmapped::new(&name, "keep", &layer.keep.join(", "), "keeping only some parameters is not supported"));
+        }
+
+        let (rules, conditional): (Vec<_>, Vec<_>) = layer.matchers().into_iter().partition(|matcher| matcher.value.is_none());
+        for matcher in conditional {
+            if let Some(condition) = &matcher.value {
+                let value = format!("{}={}", matcher.pattern, condition.pattern);
+                unmapped.push(Unmapped::new(&name, "values", &value, "value conditions are not supported"));
+            }
+        }
+
+        data.providers.insert(name, Provider {
+            url_pattern,
+            rules: rules.iter().map(|matcher| matcher.to_name_regex(NAME_CHAR)).collect(),
+            raw_rules,
+            ..Default::default()
+        });
+    }
+
+    for rule in &config.redirects {
+        if let Some(pattern) = redirection_pattern(rule) {
+            data.providers
+                .entry(rule.domain.clone())
+                .or_insert_with(|| Provider { url_pattern: domain_pattern(&rule.domain), ..Default::default() })
+                .redirections
+                .push(pattern);
+        }
+    }
+
+    (data, unmapped)
+}
+
+/// Value of a `$removeparam` filter removing the parameters of a matcher
+fn removeparam_value(matcher: &ParamMatcher) -> String {
+    let is_plain = |name: &str| name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+
+    if matcher.kind == MatcherKind::Key && matcher.value.is_none() && !matcher.ignore_case && is_plain(&matcher.pattern) {
+        return matcher.pattern.clone();
+    }
+
+    let value = match &matcher.value {
+        None => String::new(),
+        Some(condition) => match condition.kind {
+            ValueMatch::Exact => format!("{}$", regex::escape(&condition.pattern)),
+            ValueMatch::Prefix => regex::escape(&condition.pattern),
+            ValueMatch::Regex => format!("(?:{})$", condition.pattern),
+        },
+    };
+
+    // AdGuard matches the regex against `name=value`, and commas end the modifier
+    let regex = format!("{}={value}", matcher.to_name_regex(NAME_CHAR)).replace(',', r"\,");
+    let flags = if matcher.ignore_case { "i" } else { "" };
+    format!("/^{regex}/{flags}")
+}
+
+/// Write the `$removeparam` filters: rules of the global layer apply to every URL,
+/// the others to `||domain^`, exceptions become `@@` filters and `keep_only` rules keep at most one parameter.
+/// Path rules, `keep_only` rules keeping several parameters and exceptions a `@@` filter can't undo
+/// (it only disables the filter of the same name, not a regex) are reported as unmapped.
+fn removeparam_filters(config: &ConfigFile) -> (String, Vec<Unmapped>) {
+    let mut filters = String::from("! Title: boombot URL tracking rules\n");
+    let mut unmapped = Vec::new();
+    let mut global_regexes = Vec::new();
+
+    for (domain, layer) in flat_layers(config) {
+        let pattern = match domain {
+            DEFAULT_DOMAIN => String::new(),
+            domain => format!("||{domain}^"),
+        };
+
+        let mut regexes = global_regexes.clone();
+        for matcher in layer.matchers() {
+            let value = removeparam_value(&matcher);
+            let _ = writeln!(filters, "{pattern}$removeparam={value}");
+            if value.starts_with('/') {
+                regexes.push(matcher);
+            }
+        }
+
+        let regexes = MatcherSet::new(regexes);
+        for exception in &layer.exceptions {
+            let _ = writeln!(filters, "@@{pattern}$removeparam={exception}");
+            if regexes.find(exception).is_some() {
+                unmapped.push(Unmapped::new(domain, "exceptions", exception, "exceptions don't undo regex filters"));
+            }
+        }
+
+        for (field, values) in [("path_segments", &layer.path_segments), ("path_suffixes", &layer.path_suffixes), ("path_params", &layer.path_params)] {
+            for value in values {
+                unmapped.push(Unmapped::new(domain, field, value, "path rules are not supported"));
+            }
+        }
+
+        if layer.mode == RuleMode::KeepOnly {
+            match layer.keep.as_slice() {
+                _ if domain == DEFAULT_DOMAIN => {
+                    unmapped.push(Unmapped::new(domain, "keep", &layer.keep.join(", "), "keeping only some parameters of every URL is not supported"));
+                }
+                [] => {
+                    let _ = writeln!(filters, "{pattern}$removeparam");
+                }
+                [name] => {
+                    let _ = writeln!(filters, "{pattern}$removeparam=~{name}");
+                }
+                _ => {
+                    let _ = writeln!(filters, "! {domain} keeps only {} (not expressible)", layer.keep.join(", "));
+                    unmapped.push(Unmapped::new(domain, "keep", &layer.keep.join(", "), "keeping several parameters is not supported"));
+                }
+            }
+        }
+
+        if domain == DEFAULT_DOMAIN {
+            global_regexes = regexes.matchers().to_vec();
+        }
+    }
+
+    (filters, unmapped)
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DOMAIN_RULES_YAML;
+    use crate::import::{adguard, clearurls};
+
+    const YAML: &str = r#"
+defaults:
+  keys: ["gclid"]
+  starts_with: ["utm_"]
+groups:
+  social:
+    keys: ["igshid"]
+domains:
+  example.com:
+    groups: ["social"]
+    keys: ["ref"]
+    exceptions: ["utm_id"]
+  video.example:
+    mode: keep_only
+    keep: ["v"]
+  amazon.*:
+    keys: ["qid"]
+  code.example:
+    values:
+      - { key: ref, value: twitter }
+redirects:
+  - domain: out.example.com
+    path: /l.php
+    param: u
+"#;
+
+    fn config(yaml: &str) -> ConfigFile {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn yaml_and_json_exports_round_trip() {
+        let config = config(DOMAIN_RULES_YAML);
+
+        let yaml = export(&config, ExportFormat::Yaml).unwrap();
+        assert_eq!(serde_yaml::from_str::<ConfigFile>(&yaml).unwrap(), config);
+
+        let json = export(&config, ExportFormat::Json).unwrap();
+        let bundle: RuleBundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(bundle.version, BUNDLE_VERSION);
+        assert_eq!(bundle.rules, config);
+    }
+
+    #[test]
+    fn clearurls_export_can_be_imported() {
+        let json = export(&config(YAML), ExportFormat::ClearUrls).unwrap();
+        let import = clearurls::parse(&json).unwrap();
+
+        let example = import.rules.iter().find(|rule| rule.domain == "example.com").unwrap();
+        assert_eq!(example.keys, ["igshid", "ref"]);
+
+        let global = import.rules.iter().find(|rule| rule.domain == DEFAULT_DOMAIN).unwrap();
+        assert_eq!(global.keys, ["gclid"]);
+        assert_eq!(global.regexes, ["utm_[^&=]*"]);
+
+        let amazon = import.rules.iter().find(|rule| rule.domain == "amazon.*").unwrap();
+        assert_eq!(amazon.keys, ["qid"]);
+
+        assert_eq!(import.redirects, config(YAML).redirects);
+    }
+
+    #[test]
+    fn clearurls_export_reports_rules_it_cannot_express() {
+        let report = export_report(&config(YAML), ExportFormat::ClearUrls).unwrap();
+
+        let unmapped: Vec<(&str, &str, &str)> = report
+            .unmapped
+            .iter()
+            .map(|entry| (entry.source.as_str(), entry.field.as_str(), entry.value.as_str()))
+            .collect();
+        assert_eq!(unmapped, [
+            ("code.example", "values", "ref=twitter"),
+            ("example.com", "exceptions", "utm_id"),
+            ("video.example", "keep", "v"),
+        ]);
+
+        let data: DataFile = serde_json::from_str(&report.document).unwrap();
+        assert!(data.providers["example.com"].exceptions.is_empty());
+        assert!(data.providers["code.example"].rules.is_empty());
+
+        assert!(export_report(&config(YAML), ExportFormat::Json).unwrap().unmapped.is_empty());
+    }
+
+    #[test]
+    fn clearurls_export_keeps_case_insensitive_rules() {
+        let yaml = "defaults: {}\ndomains:\n  example.com:\n    ignore_case: true\n    keys: [\"Ref\"]\n    starts_with: [\"UTM_\"]\n";
+        let report = export_report(&config(yaml), ExportFormat::ClearUrls).unwrap();
+        assert!(report.unmapped.is_empty());
+
+        let import = clearurls::parse(&report.document).unwrap();
+        let example = import.rules.iter().find(|rule| rule.domain == "example.com").unwrap();
+        assert!(example.ignore_case);
+        assert_eq!(example.keys, ["Ref"]);
+        assert_eq!(example.regexes, ["UTM_[^&=]*"]);
+    }
+
+    #[test]
+    fn adguard_export_can_be_imported() {
+        let filters = export(&config(YAML), ExportFormat::AdGuard).unwrap();
+        assert!(filters.contains("\n$removeparam=/^utm_[^&=]*=/\n"));
+        assert!(filters.contains("\n@@||example.com^$removeparam=utm_id\n"));
+
+        assert!(filters.contains("\n||code.example^$removeparam=/^ref=twitter$/\n"));
+        assert!(filters.contains("\n||amazon.*^$removeparam=qid\n"));
+
+        // Value conditions are exported, but not imported back
+        let import = adguard::parse(&filters);
+        let unmapped: Vec<&str> = import.unmapped.iter().map(|entry| entry.value.as_str()).collect();
+        assert_eq!(unmapped, ["/^ref=twitter$/"]);
+
+        let example = import.rules.iter().find(|rule| rule.domain == "example.com").unwrap();
+        assert_eq!(example.keys, ["igshid", "ref"]);
+        assert_eq!(example.exceptions, ["utm_id"]);
+
+        let video = import.rules.iter().find(|rule| rule.domain == "video.example").unwrap();
+        assert_eq!(video.mode, RuleMode::KeepOnly);
+        assert_eq!(video.keep, ["v"]);
+
+        assert!(import.rules.iter().any(|rule| rule.domain == "amazon.*"));
+    }
+
+    #[test]
+    fn adguard_export_reports_rules_it_cannot_express() {
+        let yaml = r#"
+defaults:
+  keys: ["gclid"]
+  starts_with: ["utm_"]
+domains:
+  example.com:
+    exceptions: ["utm_id", "gclid"]
+  amazon.*:
+    path_segments: ["ref=*"]
+  video.example:
+    mode: keep_only
+    keep: ["v", "t"]
+"#;
+        let report = export_report(&config(yaml), ExportFormat::AdGuard).unwrap();
+
+        let unmapped: Vec<(&str, &str, &str)> = report
+            .unmapped
+            .iter()
+            .map(|entry| (entry.source.as_str(), entry.field.as_str(), entry.value.as_str()))
+            .collect();
+        assert_eq!(unmapped, [
+            ("amazon.*", "path_segments", "ref=*"),
+            ("example.com", "exceptions", "utm_id"),
+            ("video.example", "keep", "v, t"),
+        ]);
+
+        // The exception of a parameter removed by name does undo its filter
+        assert!(report.document.contains("\n@@||example.com^$removeparam=gclid\n"));
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+
+use crate::config::{DomainRule, RuleConfig, RuleMode, LAYER_DOMAIN};
+use crate::matchers::{MatcherKind, ParamMatcher};
+use crate::registry::DEFAULT_DOMAIN;
+use super::ImportReport;
+
+/// Name of the rule list, recorded as the contributor of the imported rules
+pub const SOURCE: &str = "adguard";
+
+/// Modifiers that don't change what a `$removeparam` rule does to a link
+const IGNORED_MODIFIERS: &[&str] = &["important", "document", "doc"];
+
+/// Convert the `$removeparam` rules of an AdGuard or uBlock Origin filter list into domain rules.
+/// Rules without a URL pattern go to the `default` layer, rules for `||example.com^` or with
+/// `$domain=example.com|example.org` go to those domains, and `@@` rules become exceptions.
+/// A bare `$removeparam` and `$removeparam=~name` become `keep_only` rules for a domain.
+/// Other lines of the list are skipped, and `$removeparam` rules with other modifiers,
+/// URL patterns or regexes we can't express are reported as unmapped.
+pub fn parse(list: &str) -> ImportReport {
+    let mut report = ImportReport::new(SOURCE);
+    let mut layers: BTreeMap<String, RuleConfig> = BTreeMap::new();
+
+    for (index, line) in list.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('!') || !line.contains("removeparam") {
+            continue;
+        }
+
+        let source = format!("line {}", index + 1);
+        let (exception, rule) = match line.strip_prefix("@@") {
+            Some(rule) => (true, rule),
+            None => (false, line),
+        };
+
+        let Some((pattern, modifiers)) = rule.split_once('$') else {
+            continue;
+        };
+
+        let mut param = None;
+        let mut domains = Vec::new();
+        let mut unsupported = None;
+
+        for modifier in split_modifiers(modifiers) {
+            match modifier.split_once('=') {
+                _ if modifier == "removeparam" => param = Some(""),
+                Some(("removeparam", value)) => param = Some(value),
+                Some(("domain", value)) if !value.contains('~') => domains.extend(value.split('|').map(str::to_lowercase)),
+                _ if IGNORED_MODIFIERS.contains(&modifier) => {}
+                _ => unsupported = Some(modifier),
+            }
+        }
+
+        let Some(param) = param else {
+            continue;
+        };
+        if let Some(modifier) = unsupported {
+            report.unmapped(&source, "modifier", modifier, "unsupported modifier");
+            continue;
+        }
+
+        match pattern_domain(pattern) {
+            Ok(Some(domain)) => domains.push(domain),
+            Ok(None) => {}
+            Err(reason) => {
+                report.unmapped(&source, "pattern", pattern, reason);
+                continue;
+            }
+        }
+
+        let global = domains.is_empty();
+        if global {
+            domains.push(DEFAULT_DOMAIN.to_string());
+        }
+
+        let mut config = RuleConfig::default();
+        if let Err(reason) = add_param(&mut config, param, exception, global) {
+            report.unmapped(&source, "removeparam", param, reason);
+            continue;
+        }
+
+        for domain in domains {
+            layers.entry(domain).or_default().extend(&config);
+        }
+    }
+
+    for (domain, config) in &layers {
+        report.rules.push(DomainRule {
+            contributors: vec![SOURCE.to_string()],
+            ..DomainRule::from_config(domain, LAYER_DOMAIN, config)
+        });
+    }
+
+    report
+}
+
+/// Add the rule of a `$removeparam` value to a layer
+fn add_param(config: &mut RuleConfig, param: &str, exception: bool, global: bool) -> Result<(), &'static str> {
+    if exception {
+        return match param {
+            "" => Err("exceptions for every parameter are not supported"),
+            name if name.starts_with(['/', '~']) => Err("only exceptions for a parameter name are supported"),
+            name => {
+                config.exceptions.push(name.to_string());
+                Ok(())
+            }
+        };
+    }
+
+    if param.is_empty() || param.starts_with('~') {
+        if global {
+            return Err("removing every parameter of every domain is not supported");
+        }
+
+        config.mode = RuleMode::KeepOnly;
+        config.keep.extend(param.strip_prefix('~').map(str::to_string));
+        return Ok(());
+    }
+
+    let Some(regex) = param.strip_prefix('/') else {
+        config.keys.push(param.to_string());
+        return Ok(());
+    };
+
+    let (regex, ignore_case) = match regex.strip_suffix("/i") {
+        Some(regex) => (regex, true),
+        None => (regex.strip_suffix('/').ok_or("invalid regex")?, false),
+    };
+
+    // AdGuard matches the regex against `name=value`, only regexes on the name can be used
+    let regex = regex.strip_prefix('^').ok_or("regexes on parameter values are not supported")?;
+    let regex = match regex.strip_suffix('=') {
+        Some(name) => name.to_string(),
+        None => format!("{regex}.*"),
+    };
+    if reaches_value(&regex) {
+        return Err("regexes on parameter values are not supported");
+    }
+
+    // The case sensitivity of a layer applies to all its rules, so the `i` flag stays on the regex
+    let regex = regex.replace(r"\,", ",");
+    let regex = if ignore_case { format!("(?i:{regex})") } else { regex };
+
+    let matcher = ParamMatcher::new(MatcherKind::Regex, &regex, false);
+    matcher.validate().map_err(|_| "unsupported regex syntax")?;
+
+    config.regexes.push(regex);
+    Ok(())
+}
+
+/// Whether a regex matches a `=` or the end of `name=value`, outside of its character classes
+fn reaches_value(regex: &str) -> bool {
+    let mut escaped = false;
+    let mut class = false;
+
+    regex.chars().any(|c| {
+        let reaches = !class && (c == '=' || (c == '$' && !escaped));
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => class = true,
+            ']' => class = false,
+            _ => {}
+        }
+        reaches
+    })
+}
+
+/// Get the domain of a rule's URL pattern (`||example.com^`, `||amazon.*^`), `None` for rules applying to every URL
+fn pattern_domain(pattern: &str) -> Result<Option<String>, &'static str> {
+    if pattern.is_empty() || pattern == "*" {
+        return Ok(None);
+    }
+
+    let domain = pattern
+        .strip_prefix("||")
+        .map(|domain| domain.strip_suffix('^').unwrap_or(domain))
+        .filter(|domain| {
+            // `||*.example.*^` keeps its wildcard subdomain and top-level domain
+            let name = domain.strip_prefix("*.").unwrap_or(domain);
+            let name = name.strip_suffix(".*").unwrap_or(name);
+            domain.contains('.') && !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-'))
+        })
+        .ok_or("only `||domain^` URL patterns are supported")?;
+
+    Ok(Some(domain.to_lowercase()))
+}
+
+/// Split the modifiers of a rule on commas, except inside a `removeparam` regex
+fn split_modifiers(modifiers: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut rest = modifiers;
+
+    while !rest.is_empty() {
+        let end = match rest.strip_prefix("removeparam=/") {
+            Some(regex) => regex_end(regex).map(|end| "removeparam=/".len() + end),
+            None => rest.find(','),
+        }
+        .unwrap_or(rest.len());
+
+        split.push(&rest[..end]);
+        rest = rest.get(end + 1..).unwrap_or_default();
+    }
+
+    split
+}
+
+/// Find the end of a regex after its opening `/`, including its flags
+fn regex_end(regex: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (index, c) in regex.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '/' => {
+                let end = index + 1;
+                let flags = regex[end..].find(',').map_or(regex.len(), |comma| end + comma);
+                if regex[end..flags].chars().all(|flag| flag.is_ascii_alphabetic()) {
+                    return Some(flags);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = r"! Title: URL tracking
+$removeparam=gclid
+$removeparam=/^(utm_source|utm_medium)=/
+$removeparam=/^__hs/i,important
+||example.com^$removeparam=ref
+$removeparam=si,domain=youtube.com|youtu.be
+@@||example.com^$removeparam=gclid
+||shop.example^$removeparam
+||video.example^$removeparam=~v
+example.com##.banner
+$removeparam=fbclid,xmlhttprequest
+$removeparam=/utm_/
+||example.com/path$removeparam=x
+$removeparam
+";
+
+    fn rule<'a>(report: &'a ImportReport, domain: &str) -> &'a DomainRule {
+        report.rules.iter().find(|rule| rule.domain == domain).unwrap()
+    }
+
+    #[test]
+    fn maps_removeparam_rules_to_layers() {
+        let report = parse(LIST);
+
+        let global = rule(&report, DEFAULT_DOMAIN);
+        assert_eq!(global.keys, ["gclid"]);
+        assert_eq!(global.regexes, ["(utm_source|utm_medium)", "(?i:__hs.*)"]);
+        assert!(!global.ignore_case);
+
+        let example = rule(&report, "example.com");
+        assert_eq!(example.keys, ["ref"]);
+        assert_eq!(example.exceptions, ["gclid"]);
+
+        assert_eq!(rule(&report, "youtu.be").keys, ["si"]);
+        assert_eq!(rule(&report, "youtube.com").keys, ["si"]);
+
+        assert_eq!(rule(&report, "shop.example").mode, RuleMode::KeepOnly);
+        assert!(rule(&report, "shop.example").keep.is_empty());
+        assert_eq!(rule(&report, "video.example").keep, ["v"]);
+    }
+
+    #[test]
+    fn reports_unsupported_rules() {
+        let report = parse(LIST);
+
+        let unmapped: Vec<(&str, &str, &str)> = report
+            .unmapped
+            .iter()
+            .map(|entry| (entry.source.as_str(), entry.field.as_str(), entry.value.as_str()))
+            .collect();
+        assert_eq!(unmapped, [
+            ("line 11", "modifier", "xmlhttprequest"),
+            ("line 12", "removeparam", "/utm_/"),
+            ("line 13", "pattern", "||example.com/path"),
+            ("line 14", "removeparam", ""),
+        ]);
+    }
+
+    #[test]
+    fn splits_modifiers_outside_regexes() {
+        assert_eq!(split_modifiers("removeparam=/^(a|b){1,2}=/i,domain=x.com"), ["removeparam=/^(a|b){1,2}=/i", "domain=x.com"]);
+        assert_eq!(split_modifiers("important,removeparam=ref"), ["important", "removeparam=ref"]);
+    }
+}
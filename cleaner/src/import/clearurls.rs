@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{DomainRule, RuleConfig, LAYER_DOMAIN};
+use crate::error::CleanError;
+use crate::matchers::{MatcherKind, ParamMatcher};
+use crate::redirects::RedirectRule;
+use crate::registry::{DEFAULT_DOMAIN, WILDCARD};
+use super::{ImportError, ImportReport};
+
+/// Name of the rule list, recorded as the contributor of the imported rules
+pub const SOURCE: &str = "clearurls";
+
+/// Schemes a pattern can start with
+const SCHEMES: &[&str] = &[r"https?:\/\/", "https?://", r"https:\/\/", r"http:\/\/"];
+
+/// Optional subdomains before the domain of a pattern, our domain rules always apply to subdomains
+const SUBDOMAINS: &[&str] = &[r"(?:[a-z0-9-]+\.)*?", r"(?:[a-z0-9-]+\.)*", r"([a-z0-9-]+\.)*?", r"(?:www\.)?"];
+
+/// Any top-level domain after the domain name (`amazon(?:\.[a-z]{2,}){1,}`), imported as `amazon.*`
+pub(crate) const ANY_TLD: &str = r"(?:\.[a-z]{2,}){1,}";
+
+/// What may follow the domain of a `urlPattern` matching the whole domain
+const HOST_ENDS: &[&str] = &["", "$", "/", r"\/", ".*", r"\/.*"];
+
+/// Parts of a `redirections` pattern between the path and the destination parameter
+const QUERY_STARTS: &[&str] = &[r"\?", r"\/?\?"];
+const ANY_PARAMS: &[&str] = &[".*?", ".*", "(?:.*?&)?", "(?:.*&)?"];
+const PARAM_SEPARATORS: &[&str] = &["[?&]", r"(?:\?|&)", "&"];
+
+/// Name of the provider applying to every URL
+pub(crate) const GLOBAL_PROVIDER: &str = "globalRules";
+
+/// The `data.min.json` file of ClearURLs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct DataFile {
+    pub providers: BTreeMap<String, Provider>,
+}
+
+/// A ClearURLs provider: the rules of the URLs matching `urlPattern`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Provider {
+    pub url_pattern: String,
+    #[serde(default)]
+    pub complete_provider: bool,
+    #[serde(default)]
+    pub rules: Vec<String>,
+    #[serde(default)]
+    pub referral_marketing: Vec<String>,
+    #[serde(default)]
+    pub raw_rules: Vec<String>,
+    #[serde(default)]
+    pub exceptions: Vec<String>,
+    #[serde(default)]
+    pub redirections: Vec<String>,
+    #[serde(default)]
+    pub force_redirection: bool,
+}
+
+/// Convert a ClearURLs `data.min.json` into domain and redirect rules.
+/// The `rules` and `referralMarketing` of a provider become case-insensitive keys or regexes
+/// of the domains named by its `urlPattern`, `.*` being the `default` layer,
+/// and the redirections reading a query parameter become redirect rules.
+/// URL exceptions, raw rules, blocked providers and patterns that don't reduce to domains are reported as unmapped.
+pub fn parse(json: &str) -> Result<ImportReport, CleanError> {
+    let data: DataFile = serde_json::from_str(json).map_err(ImportError::from)?;
+    let mut report = ImportReport::new(SOURCE);
+
+    for (name, provider) in &data.providers {
+        let domains = match url_pattern_domains(&provider.url_pattern) {
+            Ok(domains) => domains,
+            Err(reason) => {
+                report.unmapped(name, "urlPattern", &provider.url_pattern, reason);
+                continue;
+            }
+        };
+
+        if provider.complete_provider {
+            report.unmapped(name, "completeProvider", "true", "blocking whole domains is not supported");
+            continue;
+        }
+
+        let mut config = RuleConfig { ignore_case: true, ..Default::default() };
+        for (field, rules) in [("rules", &provider.rules), ("referralMarketing", &provider.referral_marketing)] {
+            for rule in rules {
+                if is_literal(rule) {
+                    config.keys.push(rule.clone());
+                } else if ParamMatcher::new(MatcherKind::Regex, rule, true).validate().is_ok() {
+                    config.regexes.push(rule.clone());
+                } else {
+                    report.unmapped(name, field, rule, "unsupported regex syntax");
+                }
+            }
+        }
+
+        for rule in &provider.raw_rules {
+            report.unmapped(name, "rawRules", rule, "rules on the whole URL are not supported");
+        }
+        for exception in &provider.exceptions {
+            report.unmapped(name, "exceptions", exception, "URL exceptions are not supported");
+        }
+        for redirection in &provider.redirections {
+            match redirection_rules(redirection) {
+                Ok(rules) => report.redirects.extend(rules),
+                Err(reason) => report.unmapped(name, "redirections", redirection, reason),
+            }
+        }
+
+        if config.keys.is_empty() && config.regexes.is_empty() {
+            continue;
+        }
+
+        for domain in &domains {
+            report.rules.push(DomainRule {
+                contributors: vec![SOURCE.to_string()],
+                ..DomainRule::from_config(domain, LAYER_DOMAIN, &config)
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Whether a rule is a plain parameter name rather than a regex
+fn is_literal(rule: &str) -> bool {
+    !rule.is_empty() && rule.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Get the domains of a `urlPattern` matching whole domains
+fn url_pattern_domains(pattern: &str) -> Result<Vec<String>, &'static str> {
+    if matches!(pattern, ".*" | "^.*" | ".*$") {
+        return Ok(vec![DEFAULT_DOMAIN.to_string()]);
+    }
+
+    let (domains, rest) = pattern_domains(pattern)?;
+    if !HOST_ENDS.contains(&rest) {
+        return Err("patterns restricted to some paths or queries are not supported");
+    }
+
+    Ok(domains)
+}
+
+/// Convert a redirection reading a query parameter (`^https?:\/\/l\.facebook\.com\/l\.php\?.*?u=([^&]*)`)
+/// into a redirect rule per domain and parameter name
+fn redirection_rules(pattern: &str) -> Result<Vec<RedirectRule>, &'static str> {
+    let unsupported = "only redirections reading a query parameter are supported";
+
+    let (domains, rest) = pattern_domains(pattern)?;
+    let (path, rest) = take_literal(rest, true);
+    let rest = QUERY_STARTS.iter().find_map(|start| rest.strip_prefix(start)).unwrap_or(rest);
+    let rest = ANY_PARAMS.iter().find_map(|any| rest.strip_prefix(any)).unwrap_or(rest);
+    let rest = PARAM_SEPARATORS.iter().find_map(|separator| rest.strip_prefix(separator)).unwrap_or(rest);
+
+    let (params, rest) = take_alternatives(rest, false).ok_or(unsupported)?;
+    let capture = rest.strip_prefix("=(").and_then(|capture| capture.strip_suffix(')')).ok_or(unsupported)?;
+    if capture.contains(['(', ')']) || params.iter().any(|param| param.is_empty()) {
+        return Err(unsupported);
+    }
+
+    Ok(domains
+        .iter()
+        .flat_map(|domain| {
+            params.iter().map(|param| RedirectRule {
+                domain: domain.clone(),
+                path: path.clone(),
+                param: Some(param.clone()),
+                segment: None,
+            })
+        })
+        .collect())
+}
+
+/// Read the scheme, subdomains and domains at the start of a pattern, returning the rest of the pattern
+fn pattern_domains(pattern: &str) -> Result<(Vec<String>, &str), &'static str> {
+    let rest = pattern.strip_prefix('^').ok_or("patterns not anchored to the start of the URL are not supported")?;
+    let rest = SCHEMES
+        .iter()
+        .find_map(|scheme| rest.strip_prefix(scheme))
+        .ok_or("patterns not starting with an http(s) scheme are not supported")?;
+    let rest = SUBDOMAINS.iter().find_map(|subdomains| rest.strip_prefix(subdomains)).unwrap_or(rest);
+
+    let (mut domains, rest) = take_alternatives(rest, false).ok_or("complex domain patterns are not supported")?;
+    let rest = match rest.strip_prefix(ANY_TLD) {
+        Some(rest) => {
+            domains.iter_mut().for_each(|domain| *domain = format!("{domain}.{WILDCARD}"));
+            rest
+        }
+        None => rest,
+    };
+    if domains.iter().any(|domain| !domain.contains('.') || domain.starts_with('.') || domain.ends_with('.')) {
+        return Err("complex domain patterns are not supported");
+    }
+
+    Ok((domains.into_iter().map(|domain| domain.to_lowercase()).collect(), rest))
+}
+
+/// Read a literal or a group of literal alternatives (`(?:youtube\.com|youtu\.be)`)
+fn take_alternatives(pattern: &str, path: bool) -> Option<(Vec<String>, &str)> {
+    let Some(group) = pattern.strip_prefix("(?:") else {
+        let (literal, rest) = take_literal(pattern, path);
+        return Some((vec![literal], rest));
+    };
+
+    let end = group.find(')')?;
+    let alternatives = group[..end]
+        .split('|')
+        .map(|alternative| Some(take_literal(alternative, path)).filter(|(_, rest)| rest.is_empty()).map(|(literal, _)| literal))
+        .collect::<Option<Vec<_>>>()?;
+
+    Some((alternatives, &group[end + 1..]))
+}
+
+/// Read the escaped host name or parameter name (`example\.com`), or path with `path`, at the start of a pattern
+fn take_literal(pattern: &str, path: bool) -> (String, &str) {
+    let mut literal = String::new();
+    let mut rest = pattern;
+
+    loop {
+        let mut chars = rest.chars();
+        let c = match chars.next() {
+            Some('\\') => chars.next().filter(|c| matches!(c, '.' | '-') || (path && *c == '/')),
+            Some(c) if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') => Some(c),
+            _ => None,
+        };
+
+        match c {
+            Some(c) => {
+                literal.push(c);
+                rest = chars.as_str();
+            }
+            None => return (literal, rest),
+        }
+    }
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = r#"{
+        "providers": {
+            "globalRules": {
+                "urlPattern": ".*",
+                "completeProvider": false,
+                "rules": ["(?:%3F)?utm(?:_[a-z_]*)?", "gclid", "(?!x)lookahead"],
+                "referralMarketing": ["ref_?"],
+                "rawRules": [],
+                "exceptions": ["^https?:\\/\\/[^/]+/[^/]+/[^/]+/pull/[0-9]+"],
+                "redirections": [],
+                "forceRedirection": false
+            },
+            "facebook": {
+                "urlPattern": "^https?:\\/\\/(?:[a-z0-9-]+\\.)*?facebook\\.com",
+                "rules": ["fbclid", "hc_[a-z_%\\[\\]0-9]*"],
+                "redirections": ["^https?:\\/\\/(?:[a-z0-9-]+\\.)*?facebook\\.com\\/l\\.php\\?.*?u=([^&]*)"]
+            },
+            "youtube": {
+                "urlPattern": "^https?:\\/\\/(?:[a-z0-9-]+\\.)*?(?:youtube\\.com|youtu\\.be)",
+                "rules": ["feature"],
+                "rawRules": ["\\/ref=[^/?]*"]
+            },
+            "amazon": {
+                "urlPattern": "^https?:\\/\\/(?:[a-z0-9-]+\\.)*?amazon(?:\\.[a-z]{2,}){1,}",
+                "rules": ["qid"]
+            },
+            "blocked": {
+                "urlPattern": "^https?:\\/\\/(?:[a-z0-9-]+\\.)*?tracker\\.example",
+                "completeProvider": true
+            }
+        }
+    }"#;
+
+    #[test]
+    fn maps_providers_to_domain_rules() {
+        let report = parse(DATA).unwrap();
+
+        let rules: Vec<(&str, &[String], &[String])> = report
+            .rules
+            .iter()
+            .map(|rule| (rule.domain.as_str(), rule.keys.as_slice(), rule.regexes.as_slice()))
+            .collect();
+        assert_eq!(rules, [
+            ("amazon.*", &["qid".to_string()][..], &[][..]),
+            ("facebook.com", &["fbclid".to_string()][..], &[r"hc_[a-z_%\[\]0-9]*".to_string()][..]),
+            ("default", &["gclid".to_string()][..], &["(?:%3F)?utm(?:_[a-z_]*)?".to_string(), "ref_?".to_string()][..]),
+            ("youtube.com", &["feature".to_string()][..], &[][..]),
+            ("youtu.be", &["feature".to_string()][..], &[][..]),
+        ]);
+        assert!(report.rules.iter().all(|rule| rule.ignore_case && rule.status == "pending" && rule.contributors == [SOURCE]));
+
+        assert_eq!(report.redirects, [RedirectRule {
+            domain: "facebook.com".to_string(),
+            path: "/l.php".to_string(),
+            param: Some("u".to_string()),
+            segment: None,
+        }]);
+    }
+
+    #[test]
+    fn reports_what_cannot_be_mapped() {
+        let report = parse(DATA).unwrap();
+
+        let unmapped: Vec<(&str, &str)> = report.unmapped.iter().map(|entry| (entry.source.as_str(), entry.field.as_str())).collect();
+        assert_eq!(unmapped, [
+            ("blocked", "completeProvider"),
+            ("globalRules", "rules"),
+            ("globalRules", "exceptions"),
+            ("youtube", "rawRules"),
+        ]);
+        assert_eq!(report.unmapped[0].reason, "blocking whole domains is not supported");
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(parse("{}").is_err());
+        assert!(matches!(parse("not json"), Err(CleanError::Import(_))));
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::DomainRule;
+use crate::redirects::RedirectRule;
+
+pub mod adguard;
+pub mod clearurls;
+
+/// Error reading a third-party rule list
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// An entry of a third-party rule list without an equivalent in our rule model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unmapped {
+    /// Provider or line of the list the entry comes from
+    pub source: String,
+    /// Field of the entry, such as `exceptions`
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+}
+
+impl Unmapped {
+    pub(crate) fn new(source: &str, field: &str, value: &str, reason: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            field: field.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Rules converted from a third-party rule list, to be stored as `pending` or `approved`,
+/// along with the entries that couldn't be converted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Name of the rule list, recorded as the contributor of the imported rules
+    pub source: String,
+    pub rules: Vec<DomainRule>,
+    pub redirects: Vec<RedirectRule>,
+    pub unmapped: Vec<Unmapped>,
+}
+
+impl ImportReport {
+    fn new(source: &str) -> Self {
+        Self { source: source.to_string(), ..Default::default() }
+    }
+
+    fn unmapped(&mut self, source: &str, field: &str, value: &str, reason: &str) {
+        self.unmapped.push(Unmapped::new(source, field, value, reason));
+    }
+}
@@ -1,11 +1,26 @@
 pub mod traits;
+pub mod matchers;
 pub mod cleaners;
 pub mod registry;
+pub mod redirects;
+pub mod amp;
+pub mod rewrites;
 pub mod config;
+pub mod canonical;
 pub mod engine;
+pub mod error;
+mod parts;
+pub mod report;
+pub mod text;
+#[cfg(feature = "database")]
 pub mod database;
+pub mod import;
+pub mod export;
 
-pub use engine::clean_url;
+pub use engine::{clean_url, CleanOptions, CleanerEngine, UrlProfile};
+pub use error::CleanError;
+pub use report::{CleanReport, TextReport};
+pub use rewrites::Rewrites;
 
 
 #[cfg(test)]
@@ -15,35 +30,35 @@ mod tests {
     #[test]
     fn removes_utm_parameters() {
         let input = "https://example.com?utm_source=test&utm_medium=email&utm_campaign=newsletter&param=value";
-        let expected = "https://example.com/?param=value";
+        let expected = "https://example.com?param=value";
         assert_eq!(clean_url(input).unwrap(), expected);
     }
 
     #[test]
     fn removes_instagram_tracking() {
         let input = "https://instagram.com?igsh=123&utm_source=test&param=value";
-        let expected = "https://instagram.com/?param=value";
+        let expected = "https://instagram.com?param=value";
         assert_eq!(clean_url(input).unwrap(), expected);
     }
 
     #[test]
     fn removes_linkedin_tracking() {
         let input = "https://linkedin.com?rcm=123&utm_campaign=test&param=value";
-        let expected = "https://linkedin.com/?param=value";
+        let expected = "https://linkedin.com?param=value";
         assert_eq!(clean_url(input).unwrap(), expected);
     }
 
     #[test]
     fn keeps_url_without_tracking_params() {
         let input = "https://example.com?param1=value1&param2=value2";
-        let expected = "https://example.com/?param1=value1&param2=value2";
+        let expected = "https://example.com?param1=value1&param2=value2";
         assert_eq!(clean_url(input).unwrap(), expected);
     }
 
     #[test]
     fn handles_url_without_query() {
         let input = "https://example.com";
-        let expected = "https://example.com/";
+        let expected = "https://example.com";
         assert_eq!(clean_url(input).unwrap(), expected);
     }
 
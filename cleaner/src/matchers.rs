@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+
+/// How a matcher compares its pattern with a query parameter name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatcherKind {
+    /// The name is exactly the pattern
+    Key,
+    /// The name starts with the pattern
+    Prefix,
+    /// The name ends with the pattern
+    Suffix,
+    /// The name matches a glob where `*` is any text, `?` any character and `[..]` a character class
+    Glob,
+    /// The whole name matches a regular expression
+    Regex,
+}
+
+/// How a value condition compares its pattern with a query parameter value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueMatch {
+    /// The value is exactly the pattern
+    #[default]
+    Exact,
+    /// The value starts with the pattern
+    Prefix,
+    /// The whole value matches a regular expression
+    Regex,
+}
+
+/// A condition on the (decoded) value of a query parameter, for parameters
+/// that are only tracking for some values (`ref=twitter` but not `ref=refs/heads/main`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueCondition {
+    pub kind: ValueMatch,
+    pub pattern: String,
+}
+
+impl ValueCondition {
+    pub fn new(kind: ValueMatch, pattern: &str) -> Self {
+        Self {
+            kind,
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// Compile the condition, failing on an invalid regular expression
+    fn compile(&self) -> Result<CompiledCondition, regex::Error> {
+        Ok(match self.kind {
+            ValueMatch::Exact => CompiledCondition::Exact(self.pattern.clone()),
+            ValueMatch::Prefix => CompiledCondition::Prefix(self.pattern.clone()),
+            ValueMatch::Regex => CompiledCondition::Regex(Regex::new(&format!("^(?:{})$", self.pattern))?),
+        })
+    }
+}
+
+/// A value condition ready to be tested
+#[derive(Debug)]
+enum CompiledCondition {
+    Exact(String),
+    Prefix(String),
+    Regex(Regex),
+}
+
+impl CompiledCondition {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            CompiledCondition::Exact(pattern) => value == pattern,
+            CompiledCondition::Prefix(pattern) => value.starts_with(pattern.as_str()),
+            CompiledCondition::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Layer a rule was defined in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleLayer {
+    /// The global layer, applied to every domain
+    Global,
+    /// A group layer shared by several domains
+    Group,
+    /// The layer of a single domain
+    Domain,
+}
+
+/// Where a rule comes from: its layer and the domain or group that defines it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleOrigin {
+    pub layer: RuleLayer,
+    pub name: String,
+}
+
+impl RuleOrigin {
+    pub fn new(layer: RuleLayer, name: &str) -> Self {
+        Self {
+            layer,
+            name: name.to_string(),
+        }
+    }
+}
+
+/// A rule matching query parameter names
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamMatcher {
+    pub kind: MatcherKind,
+    pub pattern: String,
+    pub ignore_case: bool,
+
+    /// Condition on the parameter value, the rule matches any value without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<ValueCondition>,
+
+    /// Layer that defined the rule, if it was loaded from a configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<RuleOrigin>,
+}
+
+impl ParamMatcher {
+    pub fn new(kind: MatcherKind, pattern: &str, ignore_case: bool) -> Self {
+        Self {
+            kind,
+            pattern: pattern.to_string(),
+            ignore_case,
+            value: None,
+            origin: None,
+        }
+    }
+
+    /// Only match the parameters whose value meets the condition
+    pub fn with_value(mut self, condition: ValueCondition) -> Self {
+        self.value = Some(condition);
+        self
+    }
+
+    /// Record the layer that defined the rule
+    pub fn with_origin(mut self, origin: RuleOrigin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Translate the matcher into a regular expression matching the whole parameter name
+    pub fn to_regex(&self) -> String {
+        let pattern = match self.kind {
+            MatcherKind::Key => format!("^{}$", regex::escape(&self.pattern)),
+            MatcherKind::Prefix => format!("^{}", regex::escape(&self.pattern)),
+            MatcherKind::Suffix => format!("{}$", regex::escape(&self.pattern)),
+            MatcherKind::Glob => format!("^{}$", glob_to_regex(&self.pattern, ".")),
+            MatcherKind::Regex => format!("^(?:{})$", self.pattern),
+        };
+
+        if self.ignore_case {
+            format!("(?i){pattern}")
+        } else {
+            pattern
+        }
+    }
+
+    /// Translate the matcher into an unanchored regular expression of the parameter name, without the case flag.
+    /// Wildcards match any run of `any_char`, so exported rules can't match past the name (`[^&=]`).
+    pub fn to_name_regex(&self, any_char: &str) -> String {
+        match self.kind {
+            MatcherKind::Key => regex::escape(&self.pattern),
+            MatcherKind::Prefix => format!("{}{any_char}*", regex::escape(&self.pattern)),
+            MatcherKind::Suffix => format!("{any_char}*{}", regex::escape(&self.pattern)),
+            MatcherKind::Glob => glob_to_regex(&self.pattern, any_char),
+            MatcherKind::Regex => format!("(?:{})", self.pattern),
+        }
+    }
+
+    /// Check that the matcher and its value condition compile, so invalid submissions can be rejected early
+    pub fn validate(&self) -> Result<(), regex::Error> {
+        Regex::new(&self.to_regex())?;
+        if let Some(condition) = &self.value {
+            condition.compile()?;
+        }
+        Ok(())
+    }
+}
+
+/// Translate a glob into an unanchored regular expression, the wildcards matching `any_char`
+pub(crate) fn glob_to_regex(glob: &str, any_char: &str) -> String {
+    let mut regex = String::new();
+    let mut rest = glob;
+
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+
+        match c {
+            '*' => {
+                regex.push_str(any_char);
+                regex.push('*');
+            }
+            '?' => regex.push_str(any_char),
+            // A character class, an unterminated one is taken literally
+            '[' if rest.contains(']') => {
+                let end = rest.find(']').unwrap_or_default();
+                let class = &rest[..end];
+                rest = &rest[end + 1..];
+
+                regex.push('[');
+                let class = match class.strip_prefix('!') {
+                    Some(negated) => {
+                        regex.push('^');
+                        negated
+                    }
+                    None => class,
+                };
+                for c in class.chars() {
+                    if matches!(c, '\\' | '[' | '^' | '&' | '~') {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+            }
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex
+}
+
+/// Matchers compiled once: exact keys are looked up in hash maps,
+/// everything else is tested in a single pass with a `RegexSet`
+#[derive(Debug)]
+pub struct MatcherSet {
+    matchers: Vec<ParamMatcher>,
+
+    /// Case sensitive exact keys, mapped to the indices of their matchers
+    keys: HashMap<String, Vec<usize>>,
+
+    /// Case insensitive exact keys (lowercased), mapped to the indices of their matchers
+    keys_ignore_case: HashMap<String, Vec<usize>>,
+
+    /// Every other matcher, with the matcher index of each pattern
+    set: RegexSet,
+    set_indices: Vec<usize>,
+
+    /// Compiled value condition of each matcher
+    conditions: Vec<Option<CompiledCondition>>,
+}
+
+impl MatcherSet {
+    /// Compile the matchers. Invalid regular expressions are skipped with a warning.
+    pub fn new(matchers: Vec<ParamMatcher>) -> Self {
+        let mut keys = HashMap::new();
+        let mut keys_ignore_case = HashMap::new();
+        let mut patterns = Vec::new();
+        let mut set_indices = Vec::new();
+        let mut conditions = Vec::new();
+
+        for (index, matcher) in matchers.iter().enumerate() {
+            let condition = matcher.value.as_ref().map(ValueCondition::compile).transpose();
+            let Ok(condition) = condition else {
+                tracing::warn!("Skipping parameter matcher {:?} with an invalid value condition", matcher.pattern);
+                conditions.push(None);
+                continue;
+            };
+            conditions.push(condition);
+
+            match matcher.kind {
+                MatcherKind::Key if matcher.ignore_case => {
+                    keys_ignore_case.entry(matcher.pattern.to_lowercase()).or_insert_with(Vec::new).push(index);
+                }
+                MatcherKind::Key => {
+                    keys.entry(matcher.pattern.clone()).or_insert_with(Vec::new).push(index);
+                }
+                _ => match matcher.validate() {
+                    Ok(()) => {
+                        patterns.push(matcher.to_regex());
+                        set_indices.push(index);
+                    }
+                    Err(e) => tracing::warn!("Skipping invalid parameter matcher {:?}: {}", matcher.pattern, e),
+                },
+            }
+        }
+
+        let set = RegexSet::new(&patterns).unwrap_or_else(|e| {
+            tracing::warn!("Failed to compile parameter matchers: {}", e);
+            set_indices.clear();
+            RegexSet::empty()
+        });
+
+        Self {
+            matchers,
+            keys,
+            keys_ignore_case,
+            set,
+            set_indices,
+            conditions,
+        }
+    }
+
+    /// Find the first matcher, in declaration order, matching the parameter name.
+    /// Matchers with a value condition are left out, use [`MatcherSet::find_with_value`] for them.
+    pub fn find(&self, name: &str) -> Option<&ParamMatcher> {
+        self.find_where(name, |condition| condition.is_none())
+    }
+
+    /// Find the first matcher, in declaration order, matching the parameter name and value
+    pub fn find_with_value(&self, name: &str, value: &str) -> Option<&ParamMatcher> {
+        self.find_where(name, |condition| condition.is_none_or(|condition| condition.matches(value)))
+    }
+
+    fn find_where(&self, name: &str, accepts: impl Fn(Option<&CompiledCondition>) -> bool) -> Option<&ParamMatcher> {
+        let exact = self.keys.get(name).into_iter().flatten();
+        let exact_ignore_case = if self.keys_ignore_case.is_empty() {
+            None
+        } else {
+            self.keys_ignore_case.get(&name.to_lowercase())
+        };
+        let patterns = self.set.matches(name).into_iter().map(|i| self.set_indices[i]).collect::<Vec<_>>();
+
+        exact
+            .chain(exact_ignore_case.into_iter().flatten())
+            .copied()
+            .chain(patterns)
+            .filter(|&index| accepts(self.conditions[index].as_ref()))
+            .min()
+            .map(|index| &self.matchers[index])
+    }
+
+    /// All the matchers of the set, in declaration order
+    pub fn matchers(&self) -> &[ParamMatcher] {
+        &self.matchers
+    }
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(matchers: &[(MatcherKind, &str, bool)]) -> MatcherSet {
+        MatcherSet::new(
+            matchers
+                .iter()
+                .map(|(kind, pattern, ignore_case)| ParamMatcher::new(*kind, pattern, *ignore_case))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn matches_every_kind() {
+        let matchers = set(&[
+            (MatcherKind::Key, "fbclid", false),
+            (MatcherKind::Prefix, "utm_", false),
+            (MatcherKind::Suffix, "_hsenc", false),
+            (MatcherKind::Glob, "mc_[ce]id", false),
+            (MatcherKind::Regex, "ga_\\d+", false),
+        ]);
+
+        assert!(matchers.find("fbclid").is_some());
+        assert!(matchers.find("utm_source").is_some());
+        assert!(matchers.find("x_hsenc").is_some());
+        assert!(matchers.find("mc_cid").is_some());
+        assert!(matchers.find("mc_eid").is_some());
+        assert!(matchers.find("ga_123").is_some());
+
+        assert!(matchers.find("mc_xid").is_none());
+        assert!(matchers.find("ga_123x").is_none());
+        assert!(matchers.find("xfbclid").is_none());
+    }
+
+    #[test]
+    fn honours_case_sensitivity() {
+        let matchers = set(&[
+            (MatcherKind::Prefix, "utm_", true),
+            (MatcherKind::Key, "Ref", true),
+            (MatcherKind::Key, "si", false),
+        ]);
+
+        assert!(matchers.find("UTM_Source").is_some());
+        assert!(matchers.find("REF").is_some());
+        assert!(matchers.find("SI").is_none());
+    }
+
+    #[test]
+    fn returns_the_first_declared_matcher() {
+        let matchers = set(&[
+            (MatcherKind::Prefix, "utm_", false),
+            (MatcherKind::Key, "utm_source", false),
+        ]);
+
+        assert_eq!(matchers.find("utm_source").unwrap().kind, MatcherKind::Prefix);
+    }
+
+    #[test]
+    fn skips_invalid_regexes() {
+        let matchers = set(&[
+            (MatcherKind::Regex, "(unclosed", false),
+            (MatcherKind::Suffix, "_hsenc", false),
+        ]);
+
+        assert!(matchers.find("x_hsenc").is_some());
+        assert!(matchers.find("(unclosed").is_none());
+    }
+
+    #[test]
+    fn translates_globs() {
+        assert_eq!(glob_to_regex("*_hsenc", "."), ".*_hsenc");
+        assert_eq!(glob_to_regex("mc_[ce]id", "."), "mc_[ce]id");
+        assert_eq!(glob_to_regex("a?[!b]", "."), "a.[^b]");
+        assert_eq!(glob_to_regex("a.b", "."), "a\\.b");
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+/// The raw parts of a URL string, sliced as they were written
+/// so cleaning can change some parts without re-serializing the others
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct UrlParts<'a> {
+    /// Scheme and authority (`https://user@example.com:8080`)
+    pub prefix: &'a str,
+    pub path: &'a str,
+    /// Query without the `?`, `Some("")` for a bare `?`
+    pub query: Option<&'a str>,
+    /// Fragment without the `#`, `Some("")` for a bare `#`
+    pub fragment: Option<&'a str>,
+}
+
+impl<'a> UrlParts<'a> {
+    pub fn split(url: &'a str) -> Self {
+        let (rest, fragment) = match url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (url, None),
+        };
+
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+
+        let authority = match rest.find("://") {
+            Some(index) => index + "://".len(),
+            None => rest.find(':').map_or(0, |index| index + 1),
+        };
+        let path = rest[authority..].find('/').map_or(rest.len(), |index| authority + index);
+
+        Self {
+            prefix: &rest[..path],
+            path: &rest[path..],
+            query,
+            fragment,
+        }
+    }
+}
+
+impl UrlParts<'_> {
+    /// Get the prefix with `host` replaced by `new_host`, keeping the scheme, credentials and port as written.
+    /// `host` is the lowercase host of the parsed URL.
+    pub fn prefix_with_host(&self, host: &str, new_host: &str) -> Option<String> {
+        let start = self.prefix.to_ascii_lowercase().find(host)?;
+        Some(format!("{}{new_host}{}", &self.prefix[..start], &self.prefix[start + host.len()..]))
+    }
+}
+
+impl fmt::Display for UrlParts<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.prefix, self.path)?;
+
+        if let Some(query) = self.query {
+            write!(f, "?{query}")?;
+        }
+        if let Some(fragment) = self.fragment {
+            write!(f, "#{fragment}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_urls_without_normalizing_them() {
+        assert_eq!(
+            UrlParts::split("HTTPS://Example.com:443/a/b;x=1?q=a%20b+c&flag#top"),
+            UrlParts {
+                prefix: "HTTPS://Example.com:443",
+                path: "/a/b;x=1",
+                query: Some("q=a%20b+c&flag"),
+                fragment: Some("top"),
+            },
+        );
+
+        assert_eq!(
+            UrlParts::split("https://example.com#/route?x=1"),
+            UrlParts {
+                prefix: "https://example.com",
+                path: "",
+                query: None,
+                fragment: Some("/route?x=1"),
+            },
+        );
+    }
+
+    #[test]
+    fn replaces_the_host_as_written() {
+        let parts = UrlParts::split("HTTPS://user@Mobile.Twitter.com:8443/a");
+        assert_eq!(
+            parts.prefix_with_host("mobile.twitter.com", "twitter.com").as_deref(),
+            Some("HTTPS://user@twitter.com:8443"),
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Maximum number of nested redirect wrappers unwrapped for a single URL
+pub const MAX_REDIRECT_DEPTH: usize = 5;
+
+/// A tracker redirect wrapper (`google.com/url?q=`, `l.facebook.com/l.php?u=`, ...)
+/// and where it stores the destination URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedirectRule {
+    /// Domain of the wrapper, subdomains included
+    pub domain: String,
+
+    /// Path the wrapper URL must start with, whole segments only (`/url` matches `/url/x` but not `/urlshortener`), any path if empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub path: String,
+
+    /// Query parameter holding the destination
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub param: Option<String>,
+
+    /// Index of the path segment where the destination starts, used when there is no `param`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment: Option<usize>,
+}
+
+impl RedirectRule {
+    /// Extract the destination of a wrapper URL, decoding percent- or base64-encoded values.
+    /// Returns `None` if the URL doesn't match the rule or doesn't hold an http(s) destination.
+    pub fn extract(&self, url: &Url) -> Option<String> {
+        if !self.matches_path(url.path()) {
+            return None;
+        }
+
+        if let Some(param) = &self.param {
+            // Query values are already percent-decoded once
+            let value = url.query_pairs().find(|(key, _)| key == param)?.1;
+            return decode_destination(&value);
+        }
+
+        let segment = self.segment?;
+        let segments: Vec<&str> = url.path_segments()?.collect();
+        let mut destination = segments.get(segment..)?.join("/");
+        if let Some(query) = url.query() {
+            destination.push('?');
+            destination.push_str(query);
+        }
+
+        decode_destination(&percent_decode_str(&destination).decode_utf8().ok()?)
+    }
+
+    /// Whether a path starts with the rule path on a segment boundary
+    fn matches_path(&self, path: &str) -> bool {
+        let Some(rest) = path.strip_prefix(&self.path) else {
+            return false;
+        };
+
+        rest.is_empty() || rest.starts_with('/') || self.path.is_empty() || self.path.ends_with('/')
+    }
+}
+
+/// Find an http(s) URL in a wrapper value, percent-decoding or base64-decoding it if needed
+fn decode_destination(value: &str) -> Option<String> {
+    let value = value.trim();
+    if is_http_url(value) {
+        return Some(value.to_string());
+    }
+
+    // Destination encoded twice
+    if let Some(decoded) = percent_decode_str(value).decode_utf8().ok().filter(|decoded| is_http_url(decoded)) {
+        return Some(decoded.into_owned());
+    }
+
+    [STANDARD, URL_SAFE, STANDARD_NO_PAD, URL_SAFE_NO_PAD]
+        .iter()
+        .filter_map(|engine| engine.decode(value).ok())
+        .filter_map(|bytes| String::from_utf8(bytes).ok())
+        .find(|decoded| is_http_url(decoded))
+}
+
+fn is_http_url(value: &str) -> bool {
+    Url::parse(value).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(domain: &str, path: &str, param: Option<&str>, segment: Option<usize>) -> RedirectRule {
+        RedirectRule {
+            domain: domain.to_string(),
+            path: path.to_string(),
+            param: param.map(String::from),
+            segment,
+        }
+    }
+
+    #[test]
+    fn extracts_percent_encoded_param() {
+        let rule = rule("google.com", "/url", Some("q"), None);
+        let url = Url::parse("https://www.google.com/url?q=https%3A%2F%2Fexample.com%2Fpage%3Fa%3D1&sa=D").unwrap();
+
+        assert_eq!(rule.extract(&url).as_deref(), Some("https://example.com/page?a=1"));
+    }
+
+    #[test]
+    fn extracts_base64_param() {
+        let rule = rule("example.net", "", Some("u"), None);
+        let url = Url::parse("https://example.net/?u=aHR0cHM6Ly9leGFtcGxlLmNvbS8_YT0x").unwrap();
+
+        assert_eq!(rule.extract(&url).as_deref(), Some("https://example.com/?a=1"));
+    }
+
+    #[test]
+    fn extracts_path_segment() {
+        let rule = rule("out.example.net", "/r/", None, Some(1));
+        let url = Url::parse("https://out.example.net/r/https%3A%2F%2Fexample.com%2Fpage").unwrap();
+
+        assert_eq!(rule.extract(&url).as_deref(), Some("https://example.com/page"));
+    }
+
+    #[test]
+    fn ignores_other_paths_and_non_urls() {
+        let rule = rule("google.com", "/url", Some("q"), None);
+
+        let search = Url::parse("https://www.google.com/search?q=https://example.com").unwrap();
+        assert_eq!(rule.extract(&search), None);
+
+        let not_a_url = Url::parse("https://www.google.com/url?q=hello").unwrap();
+        assert_eq!(rule.extract(&not_a_url), None);
+    }
+
+    #[test]
+    fn matches_paths_on_segment_boundaries() {
+        let rule = rule("google.com", "/url", Some("q"), None);
+
+        let wrapper = Url::parse("https://www.google.com/url/?q=https://example.com").unwrap();
+        assert_eq!(rule.extract(&wrapper), Some("https://example.com".to_string()));
+
+        let shortener = Url::parse("https://www.google.com/urlshortener/?q=https://example.com").unwrap();
+        assert_eq!(rule.extract(&shortener), None);
+        let other = Url::parse("https://www.google.com/url-test?q=https://example.com").unwrap();
+        assert_eq!(rule.extract(&other), None);
+    }
+}
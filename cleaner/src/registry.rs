@@ -1,21 +1,337 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use publicsuffix::{List, Psl, Type};
+
+use crate::amp::AmpRule;
 use crate::cleaners::DomainCleaner;
+use crate::error::CleanError;
+use crate::redirects::RedirectRule;
+use crate::rewrites::RewriteRule;
 
 #[cfg(test)]
 use crate::traits::UrlCleaner;
 
-/// Retrieves the appropriate URL cleaner for a given host from a String-keyed registry
-pub fn get_cleaner_for_host_string<'a>(
-    host: &str,
-    registry: &'a HashMap<String, DomainCleaner>,
-) -> &'a DomainCleaner {
-    for (domain, cleaner) in registry {
-        if host.contains(domain) {
-            return cleaner;
+/// Domain used for the fallback cleaner when no other domain matches
+pub const DEFAULT_DOMAIN: &str = "default";
+
+/// Label of a domain standing for any public suffix at its end (`amazon.*` matches `amazon.co.uk`)
+/// or for any subdomain at its start (`*.google.com` matches `mail.google.com` but not `google.com`)
+pub const WILDCARD: &str = "*";
+
+/// The public suffix list, bundled so wildcard domains are matched offline
+const PUBLIC_SUFFIX_LIST: &str = include_str!("config/public_suffix_list.dat");
+
+/// The bundled suffixes, ICANN and private ones (`github.io`). If the list didn't parse,
+/// no suffix is known and wildcard suffix domains match nothing.
+static PUBLIC_SUFFIXES: LazyLock<Option<List>> = LazyLock::new(|| match PUBLIC_SUFFIX_LIST.parse() {
+    Ok(list) => Some(list),
+    Err(e) => {
+        tracing::error!("Failed to parse the bundled public suffix list: {}", e);
+        None
+    }
+});
+
+/// A node of the domain trie, one per domain label
+struct Node<T> {
+    children: HashMap<String, Node<T>>,
+    value: Option<T>,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Self {
+            children: HashMap::new(),
+            value: None,
+        }
+    }
+}
+
+/// Values indexed by their domain labels in reverse order (`com` -> `youtube` -> `m`),
+/// so a host is resolved in O(labels) and the most specific domain always wins
+pub struct DomainTrie<T> {
+    root: Node<T>,
+    len: usize,
+}
+
+impl<T> Default for DomainTrie<T> {
+    fn default() -> Self {
+        Self {
+            root: Node::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> DomainTrie<T> {
+    /// Get the node of exactly this domain, creating the missing labels
+    fn node_mut(&mut self, domain: &str) -> &mut Node<T> {
+        normalize(domain)
+            .rsplit('.')
+            .fold(&mut self.root, |node, label| node.children.entry(label.to_string()).or_default())
+    }
+
+    /// Set the value of a domain, returning the previous one
+    pub fn insert(&mut self, domain: &str, value: T) -> Option<T> {
+        let previous = self.node_mut(domain).value.replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+
+        previous
+    }
+
+    /// Get the value of exactly this domain, inserting one if there is none yet
+    pub fn get_or_insert_with(&mut self, domain: &str, value: impl FnOnce() -> T) -> &mut T {
+        if self.get_exact(domain).is_none() {
+            self.len += 1;
+        }
+
+        self.node_mut(domain).value.get_or_insert_with(value)
+    }
+
+    /// Get the values of every domain matching the host, from the most specific to the least.
+    /// A domain matches if it is the host or a label-aligned suffix of it
+    /// (`m.youtube.com` matches `youtube.com`, `notyoutube.com` doesn't).
+    /// `amazon.*` matches `amazon` followed by any known public suffix, `*.google.com`
+    /// any subdomain of `google.com`, and a domain wins over a pattern matching as many labels.
+    pub fn matches(&self, host: &str) -> Vec<&T> {
+        let host = normalize(host);
+        let labels: Vec<&str> = host.rsplit('.').collect();
+
+        let mut found = Vec::new();
+        walk(&self.root, &labels, 0, true, &mut found);
+
+        if let Some(any_suffix) = self.root.children.get(WILDCARD) {
+            let suffix_labels = public_suffix_labels(&host).filter(|&count| count < labels.len());
+            if let Some(count) = suffix_labels {
+                walk(any_suffix, &labels[count..], count, false, &mut found);
+            }
+        }
+
+        found.sort_by_key(|&(specificity, _)| Reverse(specificity));
+        found.into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// Get the value of the most specific domain matching the host
+    pub fn get(&self, host: &str) -> Option<&T> {
+        self.matches(host).into_iter().next()
+    }
+
+    /// Get the value of exactly this domain
+    pub fn get_exact(&self, domain: &str) -> Option<&T> {
+        let mut node = &self.root;
+        for label in normalize(domain).rsplit('.') {
+            node = node.children.get(label)?;
+        }
+
+        node.value.as_ref()
+    }
+
+    /// Number of domains with a value
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Collect the values of the domains matching the rest of a host's labels (in reverse order) from a node,
+/// `matched` labels of the host having already been consumed. A domain matching more labels is more
+/// specific, then a domain is more specific than a subdomain wildcard matching the same labels,
+/// then a domain without a wildcard suffix is more specific than one with it.
+fn walk<'a, T>(mut node: &'a Node<T>, labels: &[&str], matched: usize, exact_suffix: bool, found: &mut Vec<(usize, &'a T)>) {
+    let rank = usize::from(exact_suffix);
+
+    for (index, label) in labels.iter().enumerate() {
+        // The `*` child of the root is the wildcard suffix, the others stand for the remaining subdomains
+        let at_root = index == 0 && exact_suffix;
+        let subdomains = node.children.get(WILDCARD).filter(|_| !at_root);
+        if let Some(value) = subdomains.and_then(|any| any.value.as_ref()) {
+            found.push((4 * (matched + index) + 2 + rank, value));
+        }
+
+        match node.children.get(*label) {
+            Some(child) => node = child,
+            None => return,
+        }
+
+        if let Some(value) = &node.value {
+            found.push((4 * (matched + index + 1) + rank, value));
+        }
+    }
+}
+
+/// Number of labels of the known ICANN suffix of a host (2 for `www.amazon.co.uk`),
+/// the private ones (`github.io`) not being top-level domains
+pub(crate) fn public_suffix_labels(host: &str) -> Option<usize> {
+    let list = PUBLIC_SUFFIXES.as_ref()?;
+
+    // Below a private suffix, look for the ICANN suffix it was registered under (`io` for `github.io`)
+    let mut rest = host;
+    loop {
+        let suffix = list.suffix(rest.as_bytes()).filter(|suffix| suffix.is_known())?;
+        let labels = suffix.as_bytes().split(|&byte| byte == b'.').count();
+        if suffix.typ() == Some(Type::Icann) {
+            return Some(labels);
+        }
+
+        rest = &rest[rest.len() - suffix.as_bytes().len()..];
+        rest = rest.split_once('.')?.1;
+    }
+}
+
+/// Number of labels at the end of a host matched by a rule domain, resolved like the [`DomainTrie`] does:
+/// 2 for `wikipedia.org` or `*.wikipedia.org` and `en.m.wikipedia.org`, 3 for `wikipedia.*` and `m.wikipedia.co.uk`.
+/// `None` if the domain doesn't match the host.
+pub(crate) fn matched_domain_labels(domain: &str, host: &str) -> Option<usize> {
+    let (domain, host) = (normalize(domain), normalize(host));
+    let (domain, subdomains) = match domain.strip_prefix("*.") {
+        Some(domain) => (domain, 1),
+        None => (domain.as_str(), 0),
+    };
+    let (name, suffix) = match domain.strip_suffix(".*") {
+        Some(name) => (name, public_suffix_labels(&host)?),
+        None => (domain, 0),
+    };
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let matched = name.split('.').count() + suffix;
+    let start = labels.len().checked_sub(matched + subdomains)? + subdomains;
+
+    name.split('.').eq(labels[start..labels.len() - suffix].iter().copied()).then_some(matched)
+}
+
+/// Whether a host is a public suffix (`co.uk`, `github.io`) rather than a domain someone owns
+pub(crate) fn is_public_suffix(host: &str) -> bool {
+    PUBLIC_SUFFIXES
+        .as_ref()
+        .and_then(|list| list.suffix(host.as_bytes()))
+        .is_some_and(|suffix| suffix.is_known() && suffix.as_bytes().len() == host.len())
+}
+
+/// Domain cleaners, redirect rules, AMP rules and host rewrites, resolved by host
+#[derive(Default)]
+pub struct Registry {
+    cleaners: DomainTrie<DomainCleaner>,
+    redirects: DomainTrie<Vec<RedirectRule>>,
+    amp: DomainTrie<Vec<AmpRule>>,
+    rewrites: DomainTrie<Vec<RewriteRule>>,
+    default: Option<DomainCleaner>,
+
+    /// AMP rules of the `default` domain, applied to every host
+    default_amp: Vec<AmpRule>,
+}
+
+impl Registry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the cleaner for a domain, replacing any previous one.
+    /// The `default` domain registers the fallback cleaner.
+    pub fn insert(&mut self, domain: &str, cleaner: DomainCleaner) {
+        if normalize(domain) == DEFAULT_DOMAIN {
+            self.default = Some(cleaner);
+        } else {
+            self.cleaners.insert(domain, cleaner);
+        }
+    }
+
+    /// Find the cleaner of the most specific domain matching the host
+    pub fn get(&self, host: &str) -> Option<&DomainCleaner> {
+        self.cleaners.get(host)
+    }
+
+    /// Get the fallback cleaner registered under the `default` domain
+    pub fn default_cleaner(&self) -> Option<&DomainCleaner> {
+        self.default.as_ref()
+    }
+
+    /// Check whether a cleaner is registered for exactly this domain
+    pub fn contains_domain(&self, domain: &str) -> bool {
+        if normalize(domain) == DEFAULT_DOMAIN {
+            return self.default.is_some();
         }
+
+        self.cleaners.get_exact(domain).is_some()
     }
 
-    registry.get("default").expect("default cleaner must exist")
+    /// Register a redirect wrapper rule for its domain
+    pub fn add_redirect(&mut self, rule: RedirectRule) {
+        self.redirects.get_or_insert_with(&rule.domain, Vec::new).push(rule);
+    }
+
+    /// Get the redirect rules of every domain matching the host, the most specific first
+    pub fn redirects_for(&self, host: &str) -> impl Iterator<Item = &RedirectRule> {
+        self.redirects.matches(host).into_iter().flatten()
+    }
+
+    /// Register an AMP rule for its domain, or for every domain if it is `default`
+    pub fn add_amp_rule(&mut self, rule: AmpRule) {
+        if normalize(&rule.domain) == DEFAULT_DOMAIN {
+            self.default_amp.push(rule);
+        } else {
+            self.amp.get_or_insert_with(&rule.domain, Vec::new).push(rule);
+        }
+    }
+
+    /// Get the AMP rules of every domain matching the host, the most specific first, then the `default` ones
+    pub fn amp_rules_for(&self, host: &str) -> impl Iterator<Item = &AmpRule> {
+        self.amp.matches(host).into_iter().flatten().chain(&self.default_amp)
+    }
+
+    /// Register a host rewrite for its domain, unless it is disabled
+    pub fn add_rewrite(&mut self, rule: RewriteRule) {
+        if rule.enabled {
+            self.rewrites.get_or_insert_with(&rule.domain, Vec::new).push(rule);
+        }
+    }
+
+    /// Get the host rewrites of every domain matching the host, the most specific first
+    pub fn rewrites_for(&self, host: &str) -> impl Iterator<Item = &RewriteRule> {
+        self.rewrites.matches(host).into_iter().flatten()
+    }
+
+    /// Number of registered domains, including `default`
+    pub fn len(&self) -> usize {
+        self.cleaners.len() + usize::from(self.default.is_some())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl FromIterator<(String, DomainCleaner)> for Registry {
+    fn from_iter<I: IntoIterator<Item = (String, DomainCleaner)>>(iter: I) -> Self {
+        let mut registry = Registry::new();
+        for (domain, cleaner) in iter {
+            registry.insert(&domain, cleaner);
+        }
+        registry
+    }
+}
+
+/// Lowercase the host and drop the trailing dot of fully qualified names
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Retrieves the appropriate URL cleaner for a given host from the registry,
+/// failing if no domain matches and there is no `default` cleaner
+pub fn get_cleaner_for_host_string<'a>(
+    host: &str,
+    registry: &'a Registry,
+) -> Result<&'a DomainCleaner, CleanError> {
+    registry
+        .get(host)
+        .or_else(|| registry.default_cleaner())
+        .ok_or(CleanError::MissingDefaultLayer)
 }
 
 /* =============================== */
@@ -23,35 +339,121 @@ pub fn get_cleaner_for_host_string<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::load_registry_with_fallback;
+    use crate::config::load_registry_from_file;
 
     #[test]
+    fn bundled_public_suffix_list_parses() {
+        assert!(PUBLIC_SUFFIXES.is_some());
+
+        assert_eq!(public_suffix_labels("www.amazon.co.uk"), Some(2));
+        assert_eq!(public_suffix_labels("example.com"), Some(1));
+
+        // Private suffixes are owned by one company, the ICANN suffix below them is the top-level domain
+        assert_eq!(public_suffix_labels("user.github.io"), Some(1));
+        assert!(is_public_suffix("github.io"));
+    }
+
+    #[test]
+    fn counts_the_host_labels_a_domain_matches() {
+        assert_eq!(matched_domain_labels("wikipedia.org", "en.m.wikipedia.org"), Some(2));
+        assert_eq!(matched_domain_labels("*.wikipedia.org", "en.m.wikipedia.org"), Some(2));
+        assert_eq!(matched_domain_labels("wikipedia.*", "m.wikipedia.co.uk"), Some(3));
+        assert_eq!(matched_domain_labels("*.wikipedia.*", "m.wikipedia.co.uk"), Some(3));
+
+        assert_eq!(matched_domain_labels("*.wikipedia.org", "wikipedia.org"), None);
+        assert_eq!(matched_domain_labels("wikipedia.org", "m.notwikipedia.org"), None);
+        assert_eq!(matched_domain_labels("wikipedia.*", "wikipedia.example.com"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "database")]
     fn loads_registry_from_database_with_fallback() {
-        let registry = load_registry_with_fallback("test_rules.db")
+        let registry = crate::config::load_registry_with_fallback(":memory:")
             .expect("Should load registry");
-        
+
         // Test that we can load the registry (either from DB or YAML fallback)
         assert!(!registry.is_empty());
     }
 
     #[test]
     fn gets_specific_cleaner_for_domain() {
-        let registry = load_registry_with_fallback("test_rules.db")
-            .expect("Should load registry");
-        
-        let instagram_cleaner = get_cleaner_for_host_string("www.instagram.com", &registry);
+        let registry = load_registry_from_file().expect("Should load registry");
+
+        let instagram_cleaner = get_cleaner_for_host_string("www.instagram.com", &registry).unwrap();
         assert!(instagram_cleaner.should_remove("igsh"));
-        
-        let linkedin_cleaner = get_cleaner_for_host_string("https://www.linkedin.com", &registry);
+
+        let linkedin_cleaner = get_cleaner_for_host_string("www.linkedin.com", &registry).unwrap();
         assert!(linkedin_cleaner.should_remove("rcm"));
+
+        let amazon_cleaner = get_cleaner_for_host_string("www.amazon.co.uk", &registry).unwrap();
+        assert!(amazon_cleaner.should_remove("qid"));
     }
 
     #[test]
     fn falls_back_to_default_cleaner() {
-        let registry = load_registry_with_fallback("test_rules.db")
-            .expect("Should load registry");
-        
-        let default_cleaner = get_cleaner_for_host_string("unknown.com", &registry);
+        let registry = load_registry_from_file().expect("Should load registry");
+
+        let default_cleaner = get_cleaner_for_host_string("unknown.com", &registry).unwrap();
         assert!(default_cleaner.should_remove("utm_source"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn matches_only_label_aligned_suffixes() {
+        let registry = load_registry_from_file().expect("Should load registry");
+
+        assert!(get_cleaner_for_host_string("m.facebook.com", &registry).unwrap().should_remove("fbclid"));
+        assert!(get_cleaner_for_host_string("FACEBOOK.COM.", &registry).unwrap().should_remove("fbclid"));
+
+        assert!(!get_cleaner_for_host_string("notfacebook.com", &registry).unwrap().should_remove("fbclid"));
+        assert!(!get_cleaner_for_host_string("facebook.com.evil.net", &registry).unwrap().should_remove("fbclid"));
+        assert!(!get_cleaner_for_host_string("notfacebook.com.evil.net", &registry).unwrap().should_remove("fbclid"));
+    }
+
+    #[test]
+    fn most_specific_domain_wins() {
+        let registry: Registry = [
+            ("default".to_string(), DomainCleaner::new(&[], &["utm_"])),
+            ("example.com".to_string(), DomainCleaner::new(&["generic"], &[])),
+            ("shop.example.com".to_string(), DomainCleaner::new(&["specific"], &[])),
+        ]
+        .into_iter()
+        .collect();
+
+        let cleaner = get_cleaner_for_host_string("www.shop.example.com", &registry).unwrap();
+        assert!(cleaner.should_remove("specific"));
+        assert!(!cleaner.should_remove("generic"));
+
+        let cleaner = get_cleaner_for_host_string("blog.example.com", &registry).unwrap();
+        assert!(cleaner.should_remove("generic"));
+        assert!(!cleaner.should_remove("specific"));
+    }
+
+    #[test]
+    fn matches_wildcard_suffixes_and_subdomains() {
+        let mut trie = DomainTrie::default();
+        trie.insert("amazon.*", "any suffix");
+        trie.insert("amazon.co.uk", "domain");
+        trie.insert("*.google.*", "subdomains");
+        trie.insert("*.example.com", "example subdomains");
+
+        assert_eq!(trie.matches("www.amazon.de"), [&"any suffix"]);
+        assert_eq!(trie.matches("amazon.co.jp"), [&"any suffix"]);
+        assert_eq!(trie.matches("www.amazon.co.uk"), [&"domain", &"any suffix"]);
+        assert!(trie.matches("amazon.notatld").is_empty());
+        assert!(trie.matches("amazon.github.io").is_empty());
+
+        assert_eq!(trie.matches("mail.google.com.br"), [&"subdomains"]);
+        assert!(trie.matches("google.com").is_empty());
+
+        assert_eq!(trie.matches("shop.example.com"), [&"example subdomains"]);
+        assert!(trie.matches("example.com").is_empty());
+    }
+
+    #[test]
+    fn reports_a_missing_default_cleaner() {
+        let registry: Registry = [("example.com".to_string(), DomainCleaner::new(&["ref"], &[]))].into_iter().collect();
+
+        assert!(get_cleaner_for_host_string("example.com", &registry).is_ok());
+        assert!(matches!(get_cleaner_for_host_string("unknown.com", &registry), Err(CleanError::MissingDefaultLayer)));
+    }
+}
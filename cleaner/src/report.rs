@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::amp::AmpRule;
+use crate::matchers::ParamMatcher;
+use crate::redirects::RedirectRule;
+use crate::rewrites::RewriteRule;
+
+/// Part of the URL a parameter was removed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamLocation {
+    /// A query parameter (`?utm_source=x`)
+    Query,
+    /// A `key=value` pair of the fragment (`#xtor=RSS-8`)
+    Fragment,
+    /// A whole path segment (Amazon's `/ref=sr_1_1`)
+    PathSegment,
+    /// A tracking tail cut from a path segment (`/title_ref=sr_1_1`)
+    PathSuffix,
+    /// A matrix parameter of a path segment (`;jsessionid=...`)
+    PathParam,
+}
+
+/// A parameter removed from the URL and the rule that removed it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemovedParam {
+    /// Name of the parameter, or the whole path segment
+    pub name: String,
+
+    /// Value of the parameter, empty for path segments and valueless flags
+    pub value: String,
+
+    pub location: ParamLocation,
+
+    /// Rule that matched, with the layer and domain that defined it
+    pub rule: ParamMatcher,
+}
+
+/// A tracker redirect wrapper replaced by its destination
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedirectStep {
+    pub from: String,
+    pub to: String,
+    pub rule: RedirectRule,
+}
+
+/// An AMP or mobile URL turned into the canonical page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmpStep {
+    pub from: String,
+    pub to: String,
+    pub rule: AmpRule,
+}
+
+/// A host rewritten to a privacy front-end or to its canonical form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewriteStep {
+    pub from: String,
+    pub to: String,
+    pub rule: RewriteRule,
+}
+
+/// What the engine did to a URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CleanReport {
+    pub original: String,
+    pub cleaned: String,
+
+    /// Removed parameters, in the order they appeared in the URL they were removed from
+    pub removed: Vec<RemovedParam>,
+
+    /// Redirect wrappers unwrapped before cleaning, the outermost first
+    pub redirects: Vec<RedirectStep>,
+
+    /// AMP rules applied after unwrapping the redirects, in order
+    pub amp: Vec<AmpStep>,
+
+    /// Host rewrite selected in the options and applied before cleaning
+    pub rewrite: Option<RewriteStep>,
+
+    /// Whether the cleaned URL differs from the original
+    pub changed: bool,
+}
+
+/// What the engine did to the URLs found in a piece of text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextReport {
+    pub original: String,
+
+    /// The text with every URL replaced by its cleaned version
+    pub cleaned: String,
+
+    /// Reports of the URLs found in the text, in order
+    pub urls: Vec<CleanReport>,
+
+    /// Whether any URL of the text changed
+    pub changed: bool,
+}
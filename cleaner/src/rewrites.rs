@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::parts::UrlParts;
+
+/// A rewrite of a host to a privacy-friendly front-end or to its canonical form
+/// (`twitter.com` to a Nitter instance, `youtu.be/ID` to `youtube.com/watch?v=ID`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewriteRule {
+    /// Name used to select the rewrite in the cleaning options, shared by the rules of one front-end
+    pub name: String,
+
+    /// Domain to rewrite, subdomains included
+    pub domain: String,
+
+    /// Host replacing the domain and its subdomains
+    pub target: String,
+
+    /// Template of the new path, `{0}`, `{1}`... being the segments of the original path.
+    /// It may end with query parameters placed before the original ones (`/watch?v={0}`).
+    /// The original path is kept if there is no template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Disabled rules are never applied, even when selected
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+impl RewriteRule {
+    /// Rewrite the URL if the rule applies to it. `source` is the URL as it was written,
+    /// the parts the rule doesn't touch are copied from it.
+    pub fn apply(&self, url: &Url, source: &str) -> Option<String> {
+        let host = url.host_str()?;
+        if host == self.target {
+            return None;
+        }
+
+        let parts = UrlParts::split(source);
+        let prefix = parts.prefix_with_host(host, &self.target)?;
+
+        let Some(template) = &self.path else {
+            return Some(UrlParts { prefix: &prefix, ..parts }.to_string());
+        };
+
+        let segments: Vec<&str> = parts.path.trim_start_matches('/').split('/').filter(|s| !s.is_empty()).collect();
+        let rewritten = fill_template(template, &segments)?;
+
+        let (path, query) = match rewritten.split_once('?') {
+            Some((path, query)) => {
+                let query = match parts.query.filter(|original| !original.is_empty()) {
+                    Some(original) => format!("{query}&{original}"),
+                    None => query.to_string(),
+                };
+                (path.to_string(), Some(query))
+            }
+            None => (rewritten, parts.query.map(str::to_string)),
+        };
+
+        Some(UrlParts { prefix: &prefix, path: &path, query: query.as_deref(), ..parts }.to_string())
+    }
+}
+
+/// Replace the `{n}` placeholders of a template by the path segments, `None` if one is missing
+fn fill_template(template: &str, segments: &[&str]) -> Option<String> {
+    let mut filled = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        let index: usize = rest[start + 1..end].parse().ok()?;
+
+        filled.push_str(&rest[..start]);
+        filled.push_str(segments.get(index)?);
+        rest = &rest[end + 1..];
+    }
+
+    filled.push_str(rest);
+    Some(filled)
+}
+
+/// Which host rewrites a cleaning call applies
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rewrites {
+    /// No rewrite
+    #[default]
+    None,
+    /// Every enabled rewrite
+    All,
+    /// The enabled rewrites with these names
+    Named(Vec<String>),
+}
+
+impl Rewrites {
+    /// Whether the rewrites with this name are selected
+    pub fn includes(&self, name: &str) -> bool {
+        match self {
+            Rewrites::None => false,
+            Rewrites::All => true,
+            Rewrites::Named(names) => names.iter().any(|selected| selected == name),
+        }
+    }
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(domain: &str, target: &str, path: Option<&str>) -> RewriteRule {
+        RewriteRule {
+            name: "test".to_string(),
+            domain: domain.to_string(),
+            target: target.to_string(),
+            path: path.map(String::from),
+            enabled: true,
+        }
+    }
+
+    fn apply(rule: &RewriteRule, input: &str) -> Option<String> {
+        rule.apply(&Url::parse(input).unwrap(), input)
+    }
+
+    #[test]
+    fn rewrites_hosts() {
+        let rule = rule("reddit.com", "old.reddit.com", None);
+
+        assert_eq!(apply(&rule, "https://www.reddit.com/r/rust?sort=new").as_deref(), Some("https://old.reddit.com/r/rust?sort=new"));
+        assert_eq!(apply(&rule, "https://old.reddit.com/r/rust"), None);
+    }
+
+    #[test]
+    fn fills_path_templates() {
+        let rule = rule("youtu.be", "youtube.com", Some("/watch?v={0}"));
+
+        assert_eq!(apply(&rule, "https://youtu.be/abc?t=30").as_deref(), Some("https://youtube.com/watch?v=abc&t=30"));
+        assert_eq!(apply(&rule, "https://youtu.be/abc").as_deref(), Some("https://youtube.com/watch?v=abc"));
+        assert_eq!(apply(&rule, "https://youtu.be/"), None);
+    }
+}
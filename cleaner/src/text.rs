@@ -0,0 +1,165 @@
+use std::ops::Range;
+
+/// Characters dropped from the end of a URL found in text, since they usually end the sentence
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '\'', '"', '*'];
+
+/// Find the byte ranges of the http(s) URLs in free-form text.
+///
+/// A URL ends at whitespace, at `<`, `>`, `"` or a backtick, and at a closing parenthesis
+/// or bracket it didn't open, so `<url>`, `(url)` and Markdown `[text](url)` links are found
+/// without their delimiters while `/wiki/Rust_(language)` keeps its parentheses.
+/// Trailing punctuation is left out of the URL.
+pub fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let lowercase = text.to_ascii_lowercase();
+    let mut urls = Vec::new();
+    let mut position = 0;
+
+    while let Some(offset) = lowercase[position..].find("http") {
+        let start = position + offset;
+        position = start + "http".len();
+
+        let rest = &lowercase[start..];
+        let scheme_len = if rest.starts_with("https://") {
+            "https://".len()
+        } else if rest.starts_with("http://") {
+            "http://".len()
+        } else {
+            continue;
+        };
+
+        // `xhttp://` is not a URL
+        if text[..start].chars().next_back().is_some_and(|c| c.is_alphanumeric()) {
+            continue;
+        }
+
+        let end = url_end(text, start + scheme_len);
+        if end > start + scheme_len {
+            urls.push(start..end);
+            position = end;
+        }
+    }
+
+    urls
+}
+
+/// Find where the URL starting before `from` ends
+fn url_end(text: &str, from: usize) -> usize {
+    let mut parens = 0;
+    let mut brackets = 0;
+    let mut end = text.len();
+
+    for (index, c) in text[from..].char_indices() {
+        match c {
+            '(' => parens += 1,
+            '[' => brackets += 1,
+            ')' if parens > 0 => parens -= 1,
+            ']' if brackets > 0 => brackets -= 1,
+            ')' | ']' | '<' | '>' | '"' | '`' => {
+                end = from + index;
+                break;
+            }
+            c if c.is_whitespace() => {
+                end = from + index;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    from + text[from..end].trim_end_matches(TRAILING_PUNCTUATION).len()
+}
+
+/// Replace the HTML-escaped ampersands of a URL copied from HTML (`?a=1&amp;b=2`).
+/// A URL mixing raw and escaped ampersands (`?a=1&b=2&amp;c=3`) is left as it is,
+/// since [`escape_ampersands`] couldn't tell which separators to escape again.
+pub(crate) fn unescape_ampersands(url: &str) -> Option<String> {
+    let escaped_throughout = url.match_indices('&').all(|(index, _)| starts_entity(&url[index + 1..]));
+    (escaped_throughout && url.contains("&amp;")).then(|| url.replace("&amp;", "&"))
+}
+
+/// Escape the ampersands again once the URL is cleaned, every separator having been escaped.
+/// Ampersands starting another entity (`&lt;`, `&#38;`) were left alone by [`unescape_ampersands`] and are kept as they are,
+/// while an `&amp;` can only come from a double-escaped `&amp;amp;` and is escaped again.
+pub(crate) fn escape_ampersands(url: &str) -> String {
+    let mut escaped = String::with_capacity(url.len());
+
+    for (index, c) in url.char_indices() {
+        escaped.push(c);
+        let rest = &url[index + 1..];
+        if c == '&' && (rest.starts_with("amp;") || !starts_entity(rest)) {
+            escaped.push_str("amp;");
+        }
+    }
+
+    escaped
+}
+
+/// Whether the text after an ampersand is the rest of an HTML entity (`lt;`, `#38;`, `#x26;`)
+fn starts_entity(rest: &str) -> bool {
+    let Some((name, _)) = rest.split_once(';') else {
+        return false;
+    };
+
+    match name.strip_prefix('#') {
+        Some(code) => match code.strip_prefix(['x', 'X']) {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !code.is_empty() && code.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => name.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) && name.chars().all(|c| c.is_ascii_alphanumeric()),
+    }
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(text: &str) -> Vec<&str> {
+        find_urls(text).into_iter().map(|range| &text[range]).collect()
+    }
+
+    #[test]
+    fn finds_urls_in_sentences() {
+        assert_eq!(
+            urls("Look at https://example.com/a?utm_source=x, and HTTP://example.org/b."),
+            ["https://example.com/a?utm_source=x", "HTTP://example.org/b"],
+        );
+        assert_eq!(urls("Really?! https://example.com/?id=1!!"), ["https://example.com/?id=1"]);
+    }
+
+    #[test]
+    fn handles_parentheses_and_brackets() {
+        assert_eq!(urls("(see https://example.com/page)"), ["https://example.com/page"]);
+        assert_eq!(
+            urls("https://en.wikipedia.org/wiki/Rust_(programming_language)."),
+            ["https://en.wikipedia.org/wiki/Rust_(programming_language)"],
+        );
+        assert_eq!(
+            urls("[https://example.com/?a=1](https://example.com/?utm_source=x)"),
+            ["https://example.com/?a=1", "https://example.com/?utm_source=x"],
+        );
+    }
+
+    #[test]
+    fn handles_angle_brackets_and_code() {
+        assert_eq!(urls("<https://example.com/?utm_source=x>"), ["https://example.com/?utm_source=x"]);
+        assert_eq!(urls("`https://example.com/`"), ["https://example.com/"]);
+    }
+
+    #[test]
+    fn escapes_ampersands_back_without_escaping_entities_twice() {
+        for url in ["https://example.com/?a=1&amp;b=2", "https://example.com/?a=1&amp;q=&lt;b&gt;&amp;c=&#38;", "https://example.com/?a=&amp;amp;"] {
+            let unescaped = unescape_ampersands(url).unwrap();
+            assert_eq!(escape_ampersands(&unescaped), url);
+        }
+
+        assert_eq!(escape_ampersands("https://example.com/?a=1&b=2&c"), "https://example.com/?a=1&amp;b=2&amp;c");
+        assert_eq!(unescape_ampersands("https://example.com/?a=1&b=2&amp;c=3"), None);
+    }
+
+    #[test]
+    fn ignores_non_urls() {
+        assert!(urls("xhttps://example.com http:// https is great, ftp://example.com").is_empty());
+    }
+}
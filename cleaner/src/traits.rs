@@ -1,4 +1,36 @@
+use crate::matchers::ParamMatcher;
+
 /// Trait for URL cleaners that determine which query parameters should be removed
 pub trait UrlCleaner {
-    fn should_remove(&self, key: &str) -> bool;
-}
\ No newline at end of file
+    /// Find the rule that removes the query parameter, if any
+    fn matching_rule(&self, key: &str) -> Option<&ParamMatcher>;
+
+    fn should_remove(&self, key: &str) -> bool {
+        self.matching_rule(key).is_some()
+    }
+
+    /// Find the rule that removes the query parameter with this value, if any.
+    /// Unlike `matching_rule`, it also considers the rules with a condition on the value.
+    fn matching_rule_with_value(&self, key: &str, _value: &str) -> Option<&ParamMatcher> {
+        self.matching_rule(key)
+    }
+
+    fn should_remove_with_value(&self, key: &str, value: &str) -> bool {
+        self.matching_rule_with_value(key, value).is_some()
+    }
+
+    /// Find the rule that removes a whole path segment (Amazon's `/ref=sr_1_1`), if any
+    fn matching_segment_rule(&self, _segment: &str) -> Option<&ParamMatcher> {
+        None
+    }
+
+    /// Find the rule that cuts this tail from the end of a path segment (`_ref=sr_1_1`), if any
+    fn matching_segment_suffix_rule(&self, _suffix: &str) -> Option<&ParamMatcher> {
+        None
+    }
+
+    /// Find the rule that removes a `;name=value` matrix parameter from a path segment, if any
+    fn matching_path_param_rule(&self, _name: &str) -> Option<&ParamMatcher> {
+        None
+    }
+}
@@ -1,35 +1,226 @@
+use std::error::Error;
 use std::io::{self, Write};
-use cleaner::clean_url;
+use cleaner::{CleanError, CleanOptions, CleanerEngine, Rewrites, UrlProfile};
+use cleaner::config::DOMAIN_RULES_YAML;
+use cleaner::database::Database;
+use cleaner::export::{export_report, ExportFormat};
+use cleaner::import::{adguard, clearurls};
+use cleaner::report::RemovedParam;
+
+/// Builds the cleaner engine from `DATABASE_PATH` if set, otherwise from the embedded rules
+fn build_engine() -> Result<CleanerEngine, CleanError> {
+    match std::env::var("DATABASE_PATH") {
+        Ok(path) if !path.is_empty() => CleanerEngine::from_database_with_fallback(&path),
+        _ => CleanerEngine::embedded(),
+    }
+}
+
+/// Explains why a link or the rules can't be used, and what to do about it
+fn describe_error(error: &CleanError) -> String {
+    match error {
+        CleanError::Parse(_) => "Not a URL, paste a full link such as https://example.com/page".to_string(),
+        CleanError::UnsupportedScheme(scheme) => format!("{scheme}: links can't be cleaned, only http and https links"),
+        CleanError::MissingDefaultLayer => "The rules have no `default` layer for the domains without rules of their own".to_string(),
+        CleanError::Yaml(e) => format!("The rules are not valid YAML ({e})"),
+        CleanError::Bundle(e) => format!("The rule bundle is not valid JSON ({e})"),
+        CleanError::BundleVersion(version) => format!("The rule bundle has version {version}, export it again from this version of the rules"),
+        CleanError::Import(e) => format!("The rule list can't be read ({e}), check that it is in the format of the command"),
+        CleanError::Export(e) => format!("The rules can't be exported ({e})"),
+        CleanError::Database(e) => format!("The rules database can't be read ({e}), check DATABASE_PATH"),
+    }
+}
+
+/// Explains a cleaner error like [`describe_error`], and the errors of the arguments or files as they are
+fn describe_boxed_error(error: &(dyn Error + 'static)) -> String {
+    error.downcast_ref::<CleanError>().map_or_else(|| error.to_string(), describe_error)
+}
+
+/// Describes a removed parameter and the rule that removed it, e.g. `utm_source (prefix "utm_", global layer default)`
+fn describe_removed(param: &RemovedParam) -> String {
+    let rule = &param.rule;
+    let kind = format!("{:?}", rule.kind).to_lowercase();
+    let mut description = format!("{} ({kind} \"{}\"", param.name, rule.pattern);
+
+    if let Some(origin) = &rule.origin {
+        let layer = format!("{:?}", origin.layer).to_lowercase();
+        description.push_str(&format!(", {layer} layer {}", origin.name));
+    }
+
+    description.push(')');
+    description
+}
+
+/// Builds the cleaning options from the command line: `--profile preserve|normalized|canonical`,
+/// `--strip-subdomains www,m` for the canonical profile and `--base <url>` for relative links
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<CleanOptions, String> {
+    let mut options = CleanOptions::default();
+    let mut strip_subdomains = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+
+        match arg.as_str() {
+            "--profile" => options.profile = value()?.parse()?,
+            "--strip-subdomains" => {
+                strip_subdomains = Some(value()?.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect());
+            }
+            "--strip-text-fragments" => options.strip_text_fragments = true,
+            "--base" => options.base = Some(value()?),
+            "--rewrite" => {
+                options.rewrites = match value()?.as_str() {
+                    "all" => Rewrites::All,
+                    names => Rewrites::Named(names.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()),
+                };
+            }
+            _ => return Err(format!("Unknown argument: {arg}")),
+        }
+    }
+
+    if options.profile == UrlProfile::Canonical {
+        options.strip_subdomains = strip_subdomains.unwrap_or(CleanOptions::canonical().strip_subdomains);
+    }
+
+    Ok(options)
+}
+
+/// Imports a ClearURLs `data.min.json` or an AdGuard/uBlock filter list into the rules database at `DATABASE_PATH`:
+/// `import-clearurls <file> [--approve]` or `import-adguard <file> [--approve]`
+fn import_rules(command: &str, mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let path = args.next().ok_or("Missing path to the rule list")?;
+    let status = match args.next().as_deref() {
+        None => "pending",
+        Some("--approve") => "approved",
+        Some(arg) => return Err(format!("Unknown argument: {arg}").into()),
+    };
+
+    let db_path = std::env::var("DATABASE_PATH")
+        .ok()
+        .filter(|path| !path.is_empty())
+        .ok_or("DATABASE_PATH must point to the rules database")?;
+
+    let list = std::fs::read_to_string(&path)?;
+    let import = match command {
+        "import-clearurls" => clearurls::parse(&list)?,
+        _ => adguard::parse(&list),
+    };
+    let db = Database::new(&db_path)?;
+    db.migrate_from_yaml()?;
+    let stored = db.import_rules(&import, status)?;
+
+    println!("Imported {stored} {status} rules from {path}");
+    for entry in &import.unmapped {
+        println!("  not mapped: {} {} {} ({})", entry.source, entry.field, entry.value, entry.reason);
+    }
+
+    Ok(())
+}
+
+/// Prints the approved rules of `DATABASE_PATH`, or the embedded rules, in an export format:
+/// `export yaml|json|clearurls|adguard`, with the rules a ClearURLs or AdGuard export leaves out listed on stderr
+fn export_rules(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let format: ExportFormat = args.next().ok_or("Missing export format")?.parse()?;
+
+    let config = match std::env::var("DATABASE_PATH") {
+        Ok(path) if !path.is_empty() => {
+            let db = Database::new(&path)?;
+            db.migrate_from_yaml()?;
+            db.get_approved_config()?
+        }
+        _ => serde_yaml::from_str(DOMAIN_RULES_YAML).map_err(CleanError::from)?,
+    };
+
+    let report = export_report(&config, format)?;
+    print!("{}", report.document);
+    for entry in &report.unmapped {
+        eprintln!("  not exported: {} {} {} ({})", entry.source, entry.field, entry.value, entry.reason);
+    }
+
+    Ok(())
+}
 
 fn main() {
-    println!("🧹 URL Cleaner - Paste your URL and press Enter");
+    let mut args = std::env::args().skip(1).peekable();
+    if let Some(command) = args.next_if(|arg| arg == "import-clearurls" || arg == "import-adguard") {
+        if let Err(e) = import_rules(&command, args) {
+            println!("Error: {}", describe_boxed_error(e.as_ref()));
+            println!("Usage: cli import-clearurls <data.min.json> [--approve]");
+            println!("       cli import-adguard <filters.txt> [--approve]");
+        }
+        return;
+    }
+
+    if args.next_if(|arg| arg == "export").is_some() {
+        if let Err(e) = export_rules(args) {
+            eprintln!("Error: {}", describe_boxed_error(e.as_ref()));
+            eprintln!("Usage: cli export yaml|json|clearurls|adguard");
+        }
+        return;
+    }
+
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("Error: {e}");
+            println!("Usage: cli [--profile preserve|normalized|canonical] [--strip-subdomains www,m] [--strip-text-fragments] [--rewrite all|youtube,nitter,...] [--base <url>]");
+            return;
+        }
+    };
+
+    let engine = match build_engine() {
+        Ok(engine) => engine,
+        Err(e) => {
+            println!("Error: Failed to load cleaning rules - {}", describe_error(&e));
+            return;
+        }
+    };
+
+    println!("🧹 URL Cleaner - Paste a URL or a message and press Enter");
     println!("(Press Ctrl+C to exit)");
     println!();
     
     loop {
-        print!("Enter URL: ");
+        print!("Enter URL or text: ");
         io::stdout().flush().unwrap();
         
         // Read input
         let mut input = String::new();
         match io::stdin().read_line(&mut input) {
+            // End of input (Ctrl+D or a closed pipe)
+            Ok(0) => break,
             Ok(_) => {
-                let url = input.trim();
+                let text = input.trim();
                 
-                if url.is_empty() {
+                if text.is_empty() {
                     continue;
                 }
                 
-                match clean_url(url) {
-                    Ok(cleaned) => {
-                        println!("Cleaned URL: {cleaned}");
-                        println!();
-                    },
-                    Err(e) => {
-                        println!("Error: Invalid URL - {e}");
-                        println!();
+                let report = engine.report_text_with(text, &options);
+
+                match report.urls.as_slice() {
+                    // Clean the input as a single URL to tell why it isn't one
+                    [] => match engine.report_with(text, &options) {
+                        Err(e) => println!("Error: {}", describe_error(&e)),
+                        Ok(_) => println!("Error: No http(s) URL found"),
                     },
+                    [url] if url.original == text => println!("Cleaned URL: {}", report.cleaned),
+                    _ => println!("Cleaned text: {}", report.cleaned),
                 }
+
+                for url in &report.urls {
+                    for step in &url.redirects {
+                        println!("  unwrapped redirect: {}", step.from);
+                    }
+                    for step in &url.amp {
+                        println!("  de-amped: {} -> {}", step.from, step.to);
+                    }
+                    if let Some(step) = &url.rewrite {
+                        println!("  rewrote: {} -> {}", step.from, step.to);
+                    }
+                    for param in &url.removed {
+                        println!("  removed {}", describe_removed(param));
+                    }
+                }
+                println!();
             }
             Err(e) => {
                 println!("Error reading input: {e}");
@@ -5,6 +5,7 @@ pub struct Config {
     pub token: String,
     pub application_id: String,
     pub gateway_url: String,
+    pub database_path: Option<String>,
 }
 
 impl Config {
@@ -15,6 +16,7 @@ impl Config {
             application_id: env::var("DISCORD_APPLICATION_ID")?,
             gateway_url: env::var("DISCORD_GATEWAY_URL")
                 .unwrap_or_else(|_| "wss://gateway.discord.gg/?v=10&encoding=json".to_string()),
+            database_path: env::var("DATABASE_PATH").ok().filter(|p| !p.is_empty()),
         })
     }
 }
\ No newline at end of file
@@ -21,7 +21,7 @@ pub enum BotError {
     InvalidPayload(String),
 
     #[error("URL cleaning error: {0}")]
-    CleaningError(String),
+    CleaningError(#[from] cleaner::CleanError),
 }
 
 pub type Result<T> = std::result::Result<T, BotError>;
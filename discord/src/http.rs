@@ -30,9 +30,14 @@ impl DiscordClient {
             "description": "Clean tracking parameters from a URL",
             "options": [{
                 "name": "url",
-                "description": "The URL to clean",
+                "description": "The URL or message to clean",
                 "type": 3, // String type
                 "required": true,
+            }, {
+                "name": "rewrite",
+                "description": "Rewrite links to privacy-friendly front-ends",
+                "type": 5, // Boolean type
+                "required": false,
             }]
         });
 
@@ -4,6 +4,7 @@ mod gateway;
 mod http;
 
 use crate::error::Result;
+use cleaner::{CleanError, CleanOptions, CleanerEngine, Rewrites, TextReport};
 use dotenv::dotenv;
 use tracing::{info, error};
 use tokio::time::{sleep, Duration};
@@ -29,13 +30,16 @@ async fn main() -> Result<()> {
     }
     info!("Registered /clean command");
 
+    // Load the cleaning rules once, they are reused for every interaction
+    let engine = build_engine(&config)?;
+
     let mut reconnection_delay = Duration::from_secs(1);
     let max_delay = Duration::from_secs(300); // 5 minutes
 
     loop {
         let connection_start = std::time::Instant::now();
 
-        match run_bot(&config, &http).await {
+        match run_bot(&config, &http, &engine).await {
             Ok(_) => {
                 let uptime = connection_start.elapsed();
 
@@ -62,7 +66,21 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn run_bot(config: &config::Config, http: &http::DiscordClient) -> Result<()> {
+/// Builds the cleaner engine from the shared database, or from the embedded rules if no database is configured
+fn build_engine(config: &config::Config) -> Result<CleanerEngine> {
+    match &config.database_path {
+        Some(path) => {
+            info!("Loading cleaning rules from database: {}", path);
+            Ok(CleanerEngine::from_database_with_fallback(path)?)
+        }
+        None => {
+            info!("DATABASE_PATH not set, using embedded cleaning rules");
+            Ok(CleanerEngine::embedded()?)
+        }
+    }
+}
+
+async fn run_bot(config: &config::Config, http: &http::DiscordClient, engine: &CleanerEngine) -> Result<()> {
     let mut gateway = gateway::Gateway::connect(&config.gateway_url, config.token.clone()).await?;
     gateway.identify().await?;
     info!("Connected to Discord Gateway");
@@ -81,7 +99,7 @@ async fn run_bot(config: &config::Config, http: &http::DiscordClient) -> Result<
                 // When someone uses the /clean command
                 "INTERACTION_CREATE" => {
                     if let Some(data) = event.get("d") {
-                        handle_interaction(data, http)?;
+                        handle_interaction(data, http, engine)?;
                     }
                 }
 
@@ -101,7 +119,7 @@ async fn run_bot(config: &config::Config, http: &http::DiscordClient) -> Result<
     result
 }
 
-fn handle_interaction(data: &serde_json::Value, http: &http::DiscordClient) -> Result<()> {
+fn handle_interaction(data: &serde_json::Value, http: &http::DiscordClient, engine: &CleanerEngine) -> Result<()> {
     // Get the interaction ID and token from the event data
     let interaction_id = data["id"].as_str().ok_or_else(|| 
         error::BotError::InvalidPayload("Missing interaction ID".to_string())
@@ -121,20 +139,41 @@ fn handle_interaction(data: &serde_json::Value, http: &http::DiscordClient) -> R
         return Ok(());
     }
 
-    // Navigate through the JSON structure and get the URL from the event data.
-    let url = data["data"]["options"][0]["value"]
-        .as_str()
+    // Navigate through the JSON structure and get the command options from the event data.
+    let command_options = data["data"]["options"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let option = |name: &str| command_options.iter().find(|option| option["name"] == name).map(|option| &option["value"]);
+
+    let url = option("url")
+        .and_then(|value| value.as_str())
         .ok_or_else(|| error::BotError::InvalidPayload("Missing URL parameter".to_string()))?;
 
+    // Host rewrites are opt-in through the optional `rewrite` option
+    let rewrite = option("rewrite").and_then(|value| value.as_bool()).unwrap_or(false);
+    let options = CleanOptions {
+        rewrites: if rewrite { Rewrites::All } else { Rewrites::None },
+        ..Default::default()
+    };
+
     let interaction_id = interaction_id.to_string();
     let token = token.to_string();
     let http = http.clone();
 
-    // Clean the URL
-    match cleaner::clean_url(url) {
-        Ok(cleaned) => {
+    // Clean every URL of the message, which may be a single URL.
+    // A message without any link is cleaned as a single URL to tell the user why it isn't one.
+    let report = engine.report_text_with(url, &options);
+    let result = if report.urls.is_empty() {
+        Err(match engine.report_with(url, &options) {
+            Err(e) => describe_error(&e),
+            Ok(_) => "no http(s) link found".to_string(),
+        })
+    } else {
+        Ok(report)
+    };
+
+    match result {
+        Ok(report) => {
             info!("URL cleaned successfully, sending response");
-            let response = format!("🧹 Cleaned URL:\n{cleaned}");
+            let response = format_report(&report);
 
             // @mynkie:
             // spawn: Create a new asynchronous task
@@ -154,8 +193,8 @@ fn handle_interaction(data: &serde_json::Value, http: &http::DiscordClient) -> R
             });
         }
         Err(e) => {
-            error!("Failed to clean URL: {}", e);
-            let response = format!("❌ Error cleaning URL: {e}");
+            info!("Could not clean URL: {}", e);
+            let response = format!("❌ Can't clean this: {e}");
             tokio::spawn(async move {
                 let start = std::time::Instant::now();
                 let result = http.respond_to_interaction(&interaction_id, &token, &response).await;
@@ -165,10 +204,39 @@ fn handle_interaction(data: &serde_json::Value, http: &http::DiscordClient) -> R
                     error!("Failed to respond to interaction: {}", e);
                 }
             });
-            return Err(error::BotError::CleaningError(e.to_string()));
         }
     }
 
     Ok(())
 }
 
+/// Explains to the user why their message couldn't be cleaned
+fn describe_error(error: &CleanError) -> String {
+    match error {
+        CleanError::Parse(_) => "this isn't a link, send a full URL such as `https://example.com/page`".to_string(),
+        CleanError::UnsupportedScheme(scheme) => format!("`{scheme}:` links can't be cleaned, only http and https links"),
+        // The rules are checked when the bot starts, the user can't do anything about these
+        _ => "the cleaning rules are unavailable, please try again later".to_string(),
+    }
+}
+
+/// Formats the cleaned URL or message along with the parameters that were removed
+fn format_report(report: &TextReport) -> String {
+    let mut response = match report.urls.as_slice() {
+        [url] if url.original == report.original => format!("🧹 Cleaned URL:\n{}", report.cleaned),
+        _ => format!("🧹 Cleaned message:\n{}", report.cleaned),
+    };
+
+    let removed: Vec<String> = report
+        .urls
+        .iter()
+        .flat_map(|url| &url.removed)
+        .map(|param| format!("`{}`", param.name))
+        .collect();
+
+    if !removed.is_empty() {
+        response.push_str(&format!("\nRemoved: {}", removed.join(", ")));
+    }
+
+    response
+}
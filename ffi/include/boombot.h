@@ -0,0 +1,88 @@
+#ifndef BOOMBOT_H
+#define BOOMBOT_H
+
+/* Generated by cbindgen from ffi/src/lib.rs, run `cbindgen --config ffi/cbindgen.toml --crate cleaner-ffi --output ffi/include/boombot.h` after changing it */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// URL cleaner holding a set of rules, safe to share between threads
+typedef struct BoombotEngine BoombotEngine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create an engine from a JSON rule bundle, as exported by `GET /api/rules/export/json`.
+//
+// # Safety
+//
+// `bundle` must be `NULL` or a NUL-terminated string, `error` must be `NULL` or a valid pointer.
+struct BoombotEngine *boombot_engine_from_bundle(const char *bundle, char **error);
+
+// Create an engine from the approved rules of a SQLite database, seeded with the embedded rules if it is empty.
+//
+// # Safety
+//
+// `db_path` must be `NULL` or a NUL-terminated string, `error` must be `NULL` or a valid pointer.
+struct BoombotEngine *boombot_engine_from_database(const char *db_path,
+                                                   char **error);
+
+// Free an engine, does nothing if it is `NULL`.
+//
+// # Safety
+//
+// `engine` must be `NULL` or an engine created by this library that wasn't freed yet.
+void boombot_engine_free(struct BoombotEngine *engine);
+
+// Clean a URL, unwrapping tracker redirects first.
+//
+// # Safety
+//
+// `engine` must be `NULL` or a live engine, `url` must be `NULL` or a NUL-terminated string
+// and `error` must be `NULL` or a valid pointer.
+char *boombot_clean(const struct BoombotEngine *engine, const char *url, char **error);
+
+// Clean every URL found in a text, the rest of the text is unchanged.
+//
+// # Safety
+//
+// `engine` must be `NULL` or a live engine, `text` must be `NULL` or a NUL-terminated string
+// and `error` must be `NULL` or a valid pointer.
+char *boombot_clean_text(const struct BoombotEngine *engine, const char *text, char **error);
+
+// Clean a URL and get its report as JSON: the cleaned URL, the removed parameters with their rules,
+// the unwrapped redirects and the applied AMP rules, in the format of the backend's `POST /api/clean`.
+//
+// # Safety
+//
+// `engine` must be `NULL` or a live engine, `url` must be `NULL` or a NUL-terminated string
+// and `error` must be `NULL` or a valid pointer.
+char *boombot_report(const struct BoombotEngine *engine,
+                     const char *url,
+                     char **error);
+
+// Clean every URL found in a text and get the report as JSON: the cleaned text and the report of each URL.
+//
+// # Safety
+//
+// `engine` must be `NULL` or a live engine, `text` must be `NULL` or a NUL-terminated string
+// and `error` must be `NULL` or a valid pointer.
+char *boombot_report_text(const struct BoombotEngine *engine,
+                          const char *text,
+                          char **error);
+
+// Free a string returned by the library, does nothing if it is `NULL`.
+//
+// # Safety
+//
+// `string` must be `NULL` or a string returned by this library that wasn't freed yet.
+void boombot_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BOOMBOT_H */
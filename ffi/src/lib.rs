@@ -0,0 +1,246 @@
+//! C API of the cleaner, for applications written in other languages.
+//!
+//! An engine is created from a JSON rule bundle or a rules database and freed with
+//! [`boombot_engine_free`]. Every string it returns is allocated by the library and must be
+//! freed with [`boombot_string_free`]. Functions that can fail return `NULL` and, if `error`
+//! isn't `NULL`, set `*error` to a message that must be freed the same way.
+
+use std::ffi::{c_char, CStr, CString, NulError};
+use std::ptr;
+
+use cleaner::{CleanError, CleanerEngine};
+use thiserror::Error;
+
+/// URL cleaner holding a set of rules, safe to share between threads
+pub struct BoombotEngine {
+    engine: CleanerEngine,
+}
+
+/// Error of a call, given back to the caller as a message
+#[derive(Debug, Error)]
+enum FfiError {
+    #[error("`{0}` is NULL")]
+    Null(&'static str),
+
+    #[error("`{0}` is not valid UTF-8")]
+    Utf8(&'static str),
+
+    #[error(transparent)]
+    Clean(#[from] CleanError),
+
+    #[error("the report can't be written as JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("the result contains a NUL byte")]
+    Nul(#[from] NulError),
+}
+
+/// Create an engine from a JSON rule bundle, as exported by `GET /api/rules/export/json`.
+///
+/// # Safety
+///
+/// `bundle` must be `NULL` or a NUL-terminated string, `error` must be `NULL` or a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boombot_engine_from_bundle(bundle: *const c_char, error: *mut *mut c_char) -> *mut BoombotEngine {
+    let engine = unsafe { read_str(bundle, "bundle") }.and_then(|bundle| Ok(CleanerEngine::from_bundle(bundle)?));
+    unsafe { engine_or_error(engine, error) }
+}
+
+/// Create an engine from the approved rules of a SQLite database, seeded with the embedded rules if it is empty.
+///
+/// # Safety
+///
+/// `db_path` must be `NULL` or a NUL-terminated string, `error` must be `NULL` or a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boombot_engine_from_database(db_path: *const c_char, error: *mut *mut c_char) -> *mut BoombotEngine {
+    let engine = unsafe { read_str(db_path, "db_path") }.and_then(|db_path| Ok(CleanerEngine::from_database(db_path)?));
+    unsafe { engine_or_error(engine, error) }
+}
+
+/// Free an engine, does nothing if it is `NULL`.
+///
+/// # Safety
+///
+/// `engine` must be `NULL` or an engine created by this library that wasn't freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boombot_engine_free(engine: *mut BoombotEngine) {
+    if !engine.is_null() {
+        drop(unsafe { Box::from_raw(engine) });
+    }
+}
+
+/// Clean a URL, unwrapping tracker redirects first.
+///
+/// # Safety
+///
+/// `engine` must be `NULL` or a live engine, `url` must be `NULL` or a NUL-terminated string
+/// and `error` must be `NULL` or a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boombot_clean(engine: *const BoombotEngine, url: *const c_char, error: *mut *mut c_char) -> *mut c_char {
+    let cleaned = unsafe { with_input(engine, url, "url") }.and_then(|(engine, url)| Ok(engine.clean(url)?));
+    unsafe { string_or_error(cleaned, error) }
+}
+
+/// Clean every URL found in a text, the rest of the text is unchanged.
+///
+/// # Safety
+///
+/// `engine` must be `NULL` or a live engine, `text` must be `NULL` or a NUL-terminated string
+/// and `error` must be `NULL` or a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boombot_clean_text(engine: *const BoombotEngine, text: *const c_char, error: *mut *mut c_char) -> *mut c_char {
+    let cleaned = unsafe { with_input(engine, text, "text") }.map(|(engine, text)| engine.clean_text(text));
+    unsafe { string_or_error(cleaned, error) }
+}
+
+/// Clean a URL and get its report as JSON: the cleaned URL, the removed parameters with their rules,
+/// the unwrapped redirects and the applied AMP rules, in the format of the backend's `POST /api/clean`.
+///
+/// # Safety
+///
+/// `engine` must be `NULL` or a live engine, `url` must be `NULL` or a NUL-terminated string
+/// and `error` must be `NULL` or a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boombot_report(engine: *const BoombotEngine, url: *const c_char, error: *mut *mut c_char) -> *mut c_char {
+    let report = unsafe { with_input(engine, url, "url") }
+        .and_then(|(engine, url)| Ok(serde_json::to_string(&engine.report(url)?)?));
+    unsafe { string_or_error(report, error) }
+}
+
+/// Clean every URL found in a text and get the report as JSON: the cleaned text and the report of each URL.
+///
+/// # Safety
+///
+/// `engine` must be `NULL` or a live engine, `text` must be `NULL` or a NUL-terminated string
+/// and `error` must be `NULL` or a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boombot_report_text(engine: *const BoombotEngine, text: *const c_char, error: *mut *mut c_char) -> *mut c_char {
+    let report = unsafe { with_input(engine, text, "text") }
+        .and_then(|(engine, text)| Ok(serde_json::to_string(&engine.report_text(text))?));
+    unsafe { string_or_error(report, error) }
+}
+
+/// Free a string returned by the library, does nothing if it is `NULL`.
+///
+/// # Safety
+///
+/// `string` must be `NULL` or a string returned by this library that wasn't freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boombot_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Read a string argument
+unsafe fn read_str<'a>(string: *const c_char, name: &'static str) -> Result<&'a str, FfiError> {
+    if string.is_null() {
+        return Err(FfiError::Null(name));
+    }
+
+    unsafe { CStr::from_ptr(string) }.to_str().map_err(|_| FfiError::Utf8(name))
+}
+
+/// Read the engine and the string argument of a cleaning call
+unsafe fn with_input<'a>(engine: *const BoombotEngine, input: *const c_char, name: &'static str) -> Result<(&'a CleanerEngine, &'a str), FfiError> {
+    let engine = unsafe { engine.as_ref() }.ok_or(FfiError::Null("engine"))?;
+    Ok((&engine.engine, unsafe { read_str(input, name) }?))
+}
+
+/// Give an engine to the caller, or `NULL` and the error message
+unsafe fn engine_or_error(engine: Result<CleanerEngine, FfiError>, error: *mut *mut c_char) -> *mut BoombotEngine {
+    match engine {
+        Ok(engine) => Box::into_raw(Box::new(BoombotEngine { engine })),
+        Err(e) => {
+            unsafe { set_error(error, e) };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Give a string to the caller, or `NULL` and the error message
+unsafe fn string_or_error(string: Result<String, FfiError>, error: *mut *mut c_char) -> *mut c_char {
+    match string.and_then(|string| Ok(CString::new(string)?)) {
+        Ok(string) => string.into_raw(),
+        Err(e) => {
+            unsafe { set_error(error, e) };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Write the message of an error to `*error`, if the caller asked for it
+unsafe fn set_error(error: *mut *mut c_char, e: FfiError) {
+    if let Some(error) = unsafe { error.as_mut() } {
+        *error = CString::new(e.to_string()).unwrap_or_default().into_raw();
+    }
+}
+
+/* =============================== */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUNDLE: &CStr = cr#"{"version": 1, "defaults": {"starts_with": ["utm_"]}, "domains": {"example.com": {"keys": ["ref"]}}}"#;
+
+    /// Take a string returned by the library
+    fn take(string: *mut c_char) -> String {
+        assert!(!string.is_null());
+        let owned = unsafe { CStr::from_ptr(string) }.to_str().unwrap().to_string();
+        unsafe { boombot_string_free(string) };
+        owned
+    }
+
+    #[test]
+    fn cleans_urls_and_texts_with_a_bundle() {
+        let engine = unsafe { boombot_engine_from_bundle(BUNDLE.as_ptr(), ptr::null_mut()) };
+        assert!(!engine.is_null());
+
+        let cleaned = unsafe { boombot_clean(engine, c"https://example.com/?ref=a&utm_source=b&id=1".as_ptr(), ptr::null_mut()) };
+        assert_eq!(take(cleaned), "https://example.com/?id=1");
+
+        let cleaned = unsafe { boombot_clean_text(engine, c"see https://example.com/?ref=a".as_ptr(), ptr::null_mut()) };
+        assert_eq!(take(cleaned), "see https://example.com/");
+
+        let report = unsafe { boombot_report(engine, c"https://example.com/?ref=a".as_ptr(), ptr::null_mut()) };
+        let report: cleaner::CleanReport = serde_json::from_str(&take(report)).unwrap();
+        assert_eq!(report.removed[0].name, "ref");
+
+        let report = unsafe { boombot_report_text(engine, c"see https://example.com/?ref=a".as_ptr(), ptr::null_mut()) };
+        let report: cleaner::TextReport = serde_json::from_str(&take(report)).unwrap();
+        assert_eq!(report.urls.len(), 1);
+
+        unsafe { boombot_engine_free(engine) };
+    }
+
+    #[test]
+    fn loads_rules_from_a_database() {
+        let engine = unsafe { boombot_engine_from_database(c":memory:".as_ptr(), ptr::null_mut()) };
+        assert!(!engine.is_null());
+
+        let cleaned = unsafe { boombot_clean(engine, c"https://youtu.be/abc?si=xyz&t=30".as_ptr(), ptr::null_mut()) };
+        assert_eq!(take(cleaned), "https://youtu.be/abc?t=30");
+
+        unsafe { boombot_engine_free(engine) };
+    }
+
+    #[test]
+    fn reports_errors_through_the_error_pointer() {
+        let mut error = ptr::null_mut();
+        let engine = unsafe { boombot_engine_from_bundle(c"{}".as_ptr(), &mut error) };
+        assert!(engine.is_null());
+        assert!(take(error).starts_with("invalid rule bundle"));
+
+        let engine = unsafe { boombot_engine_from_bundle(BUNDLE.as_ptr(), ptr::null_mut()) };
+        let cleaned = unsafe { boombot_clean(engine, c"mailto:someone@example.com".as_ptr(), &mut error) };
+        assert!(cleaned.is_null());
+        assert!(take(error).starts_with("unsupported scheme"));
+
+        let cleaned = unsafe { boombot_clean(engine, ptr::null(), &mut error) };
+        assert!(cleaned.is_null());
+        assert_eq!(take(error), "`url` is NULL");
+
+        unsafe { boombot_engine_free(engine) };
+    }
+}
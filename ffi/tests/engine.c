@@ -0,0 +1,87 @@
+/*
+ * Exercises the C API of the cleaner. Build the library, then from the repository root:
+ *
+ *   gcc -Wall -Wextra -Werror ffi/tests/engine.c -Iffi/include -Ltarget/debug -lboombot -o target/ffi-engine
+ *   LD_LIBRARY_PATH=target/debug target/ffi-engine
+ */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "boombot.h"
+
+static int failures = 0;
+
+/* Check a string returned by the library, then free it */
+static void expect(const char *name, char *actual, const char *expected) {
+    if (actual == NULL || strcmp(actual, expected) != 0) {
+        fprintf(stderr, "%s: expected \"%s\", got \"%s\"\n", name, expected, actual ? actual : "NULL");
+        failures++;
+    }
+    boombot_string_free(actual);
+}
+
+/* Check that a string returned by the library contains a part, then free it */
+static void expect_contains(const char *name, char *actual, const char *part) {
+    if (actual == NULL || strstr(actual, part) == NULL) {
+        fprintf(stderr, "%s: expected \"%s\" in \"%s\"\n", name, part, actual ? actual : "NULL");
+        failures++;
+    }
+    boombot_string_free(actual);
+}
+
+static const char *BUNDLE =
+    "{\"version\": 1,"
+    " \"defaults\": {\"starts_with\": [\"utm_\"]},"
+    " \"domains\": {\"example.com\": {\"keys\": [\"ref\"]}}}";
+
+int main(void) {
+    char *error = NULL;
+
+    BoombotEngine *engine = boombot_engine_from_bundle(BUNDLE, &error);
+    if (engine == NULL) {
+        fprintf(stderr, "can't load the bundle: %s\n", error);
+        boombot_string_free(error);
+        return 1;
+    }
+
+    expect("clean", boombot_clean(engine, "https://example.com/?ref=a&utm_source=b&id=1", NULL), "https://example.com/?id=1");
+    expect("clean_text", boombot_clean_text(engine, "see https://example.com/?ref=a", NULL), "see https://example.com/");
+    expect_contains("report", boombot_report(engine, "https://example.com/?ref=a", NULL), "\"cleaned\":\"https://example.com/\"");
+    expect_contains("report_text", boombot_report_text(engine, "see https://example.com/?ref=a", NULL), "\"cleaned\":\"see https://example.com/\"");
+
+    char *cleaned = boombot_clean(engine, "mailto:someone@example.com", &error);
+    if (cleaned != NULL) {
+        fprintf(stderr, "clean: expected an error for a mailto: link\n");
+        failures++;
+    }
+    expect_contains("clean error", error, "unsupported scheme");
+
+    boombot_engine_free(engine);
+
+    error = NULL;
+    engine = boombot_engine_from_database(":memory:", &error);
+    if (engine == NULL) {
+        fprintf(stderr, "can't load the database: %s\n", error);
+        boombot_string_free(error);
+        return 1;
+    }
+
+    expect("clean from database", boombot_clean(engine, "https://youtu.be/abc?si=xyz&t=30", NULL), "https://youtu.be/abc?t=30");
+    boombot_engine_free(engine);
+
+    error = NULL;
+    if (boombot_engine_from_bundle("{}", &error) != NULL) {
+        fprintf(stderr, "from_bundle: expected an error for an empty bundle\n");
+        failures++;
+    }
+    expect_contains("bundle error", error, "invalid rule bundle");
+
+    if (failures > 0) {
+        fprintf(stderr, "%d check(s) failed\n", failures);
+        return 1;
+    }
+
+    printf("All C API checks passed\n");
+    return 0;
+}
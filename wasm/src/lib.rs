@@ -0,0 +1,44 @@
+//! WebAssembly bindings of the cleaner core, for the browser extension and the web app.
+//! The rules come from a JSON rule bundle (`GET /api/rules/export/json`), and the reports
+//! are given back as plain JavaScript objects with the same fields as the API responses.
+
+use cleaner::CleanerEngine;
+use serde::Serialize;
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::prelude::*;
+
+/// URL cleaner holding the rules of a bundle, build it once and reuse it for every link
+#[wasm_bindgen]
+pub struct Cleaner {
+    engine: CleanerEngine,
+}
+
+#[wasm_bindgen]
+impl Cleaner {
+    /// Load the rules of a JSON rule bundle, throws if the bundle is invalid or has another version
+    #[wasm_bindgen(constructor)]
+    pub fn new(bundle: &str) -> Result<Cleaner, JsError> {
+        Ok(Self { engine: CleanerEngine::from_bundle(bundle)? })
+    }
+
+    /// Use the rules embedded in the library, for when no bundle could be downloaded
+    pub fn embedded() -> Result<Cleaner, JsError> {
+        Ok(Self { engine: CleanerEngine::embedded()? })
+    }
+
+    /// Clean a URL and get its `CleanReport`, throws if it isn't an http or https link
+    pub fn clean(&self, url: &str) -> Result<JsValue, JsError> {
+        to_js(&self.engine.report(url)?)
+    }
+
+    /// Clean every link of a text and get its `TextReport`, with a `CleanReport` for each link
+    #[wasm_bindgen(js_name = cleanText)]
+    pub fn clean_text(&self, text: &str) -> Result<JsValue, JsError> {
+        to_js(&self.engine.report_text(text))
+    }
+}
+
+/// Convert a report to a plain object, with `null` for the missing values instead of `undefined`
+fn to_js(report: &impl Serialize) -> Result<JsValue, JsError> {
+    Ok(report.serialize(&Serializer::json_compatible())?)
+}
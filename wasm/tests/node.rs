@@ -0,0 +1,60 @@
+//! Run with `wasm-pack test --node wasm`
+#![cfg(target_arch = "wasm32")]
+
+use cleaner::{CleanReport, TextReport};
+use cleaner_wasm::Cleaner;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const BUNDLE: &str = r#"{
+    "version": 1,
+    "defaults": { "starts_with": ["utm_"] },
+    "domains": { "example.com": { "keys": ["ref"] } },
+    "redirects": [{ "domain": "out.example.com", "path": "/click", "param": "url" }]
+}"#;
+
+fn clean(url: &str) -> CleanReport {
+    let report = Cleaner::new(BUNDLE).unwrap().clean(url).unwrap();
+    serde_wasm_bindgen::from_value(report).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn cleans_urls_with_the_bundle_rules() {
+    let report = clean("https://example.com/page?ref=abc&utm_source=x&id=1");
+
+    assert_eq!(report.cleaned, "https://example.com/page?id=1");
+    assert!(report.changed);
+    let removed: Vec<&str> = report.removed.iter().map(|param| param.name.as_str()).collect();
+    assert_eq!(removed, ["ref", "utm_source"]);
+}
+
+#[wasm_bindgen_test]
+fn unwraps_redirects_of_the_bundle() {
+    let report = clean("https://out.example.com/click?url=https%3A%2F%2Fexample.com%2F%3Fref%3Dabc");
+
+    assert_eq!(report.cleaned, "https://example.com/");
+    assert_eq!(report.redirects.len(), 1);
+}
+
+#[wasm_bindgen_test]
+fn cleans_every_link_of_a_text() {
+    let report = Cleaner::new(BUNDLE).unwrap().clean_text("see https://example.com/?ref=abc and https://other.com/?utm_medium=x").unwrap();
+    let report: TextReport = serde_wasm_bindgen::from_value(report).unwrap();
+
+    assert_eq!(report.cleaned, "see https://example.com/ and https://other.com/");
+    assert_eq!(report.urls.len(), 2);
+}
+
+#[wasm_bindgen_test]
+fn rejects_invalid_bundles_and_links() {
+    assert!(Cleaner::new("{}").is_err());
+    assert!(Cleaner::new(&BUNDLE.replace("\"version\": 1", "\"version\": 2")).is_err());
+    assert!(Cleaner::new(BUNDLE).unwrap().clean("mailto:someone@example.com").is_err());
+}
+
+#[wasm_bindgen_test]
+fn falls_back_to_the_embedded_rules() {
+    let report = Cleaner::embedded().unwrap().clean("https://youtu.be/abc?si=xyz&t=30").unwrap();
+    let report: CleanReport = serde_wasm_bindgen::from_value(report).unwrap();
+
+    assert_eq!(report.cleaned, "https://youtu.be/abc?t=30");
+}
@@ -69,7 +69,7 @@ export default function AddRuleForm({ onSuccess }: AddRuleFormProps) {
 
                 <Input
                     type="text"
-                    placeholder="e.g. twitter.com"
+                    placeholder="e.g. twitter.com, amazon.* or *.google.*"
                     value={formData.domain}
                     onChange={(e) => setFormData(prev => ({ ...prev, domain: e.target.value }))}
                     required
@@ -44,6 +44,7 @@ export default function RulesTable({ title, rules, type, onAction, isAdmin = fal
                             }
 
                             <TableHead>Domain</TableHead>
+                            <TableHead>Mode</TableHead>
                             <TableHead>Keys</TableHead>
                             <TableHead>Starts With</TableHead>
                             
@@ -68,6 +69,12 @@ export default function RulesTable({ title, rules, type, onAction, isAdmin = fal
                                     </TableCell>
                                 )}
                                 <TableCell>{rule.domain}</TableCell>
+                                <TableCell>
+                                    {rule.mode === 'keep_only'
+                                        ? `Keep only: ${rule.keep?.length ? rule.keep.join(', ') : 'nothing'}`
+                                        : 'Remove'
+                                    }
+                                </TableCell>
                                 <TableCell>{rule.keys.join(', ')}</TableCell>
                                 <TableCell>{rule.starts_with.join(', ')}</TableCell>
                                 {type === 'pending' && isAdmin && (
@@ -2,6 +2,12 @@ import type { ApiResponse } from '../types/api';
 import { API_CONFIG } from '../config/api';
 
 export class BaseApiService {
+    // Token of the logged in admin, required by the review and admin routes
+    protected static authHeaders(): Record<string, string> {
+        const token = localStorage.getItem('admin_token');
+        return token ? { Authorization: `Bearer ${token}` } : {};
+    }
+
     protected static async handleResponse<T>(response: Response): Promise<ApiResponse<T>> {
         if (response.ok) {
             return await response.json();
@@ -27,7 +33,7 @@ export class BaseApiService {
     ): Promise<TResponse> {
         const response = await fetch(`${API_CONFIG.BASE_URL}${endpoint}`, {
             method: 'POST',
-            headers: API_CONFIG.HEADERS.JSON,
+            headers: { ...API_CONFIG.HEADERS.JSON, ...this.authHeaders() },
             body: JSON.stringify(data),
         });
 
@@ -42,7 +48,8 @@ export class BaseApiService {
 
     protected static async put<T = void>(endpoint: string): Promise<T> {
         const response = await fetch(`${API_CONFIG.BASE_URL}${endpoint}`, {
-            method: 'PUT'
+            method: 'PUT',
+            headers: this.authHeaders(),
         });
 
         const result = await this.handleResponse<T>(response);
@@ -1,8 +1,31 @@
+export type RuleMode = 'remove' | 'keep_only';
+
+export type ValueMatch = 'exact' | 'prefix' | 'regex';
+
+export interface ValueRule {
+    key: string;
+    kind?: ValueMatch;
+    value: string;
+}
+
 export interface DomainRule {
     id: number;
     domain: string;
     keys: string[];
+    layer?: string;
     starts_with: string[];
+    ends_with?: string[];
+    patterns?: string[];
+    regexes?: string[];
+    path_segments?: string[];
+    path_suffixes?: string[];
+    path_params?: string[];
+    ignore_case?: boolean;
+    exceptions?: string[];
+    groups?: string[];
+    mode?: RuleMode;
+    keep?: string[];
+    values?: ValueRule[];
     contributors?: string[];
     status: string;
 }
@@ -18,6 +41,18 @@ export interface SubmissionRequest {
     domain: string;
     keys: string[];
     starts_with?: string[];
+    ends_with?: string[];
+    patterns?: string[];
+    regexes?: string[];
+    path_segments?: string[];
+    path_suffixes?: string[];
+    path_params?: string[];
+    ignore_case?: boolean;
+    exceptions?: string[];
+    groups?: string[];
+    mode?: RuleMode;
+    keep?: string[];
+    values?: ValueRule[];
     contributor: string;
 }
 